@@ -1,39 +1,50 @@
-use engine::{Engine, Float, Game};
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
+use engine::{Color, Engine, Event, Float, Game, Key, Vec2};
+
+const SIZE: u32 = 20;
 
 struct BasicGame {
-    x: Float,
-    y: Float,
+    position: Vec2<Float>,
     speed: Float,
 }
 
 impl Game for BasicGame {
+    fn handle_event(&mut self, event: Event) -> Option<Event> {
+        // Keeps the square on screen when the browser window shrinks
+        if let Event::Resized { width, height } = event {
+            self.position.x = self.position.x.min(width.saturating_sub(SIZE) as Float);
+            self.position.y = self.position.y.min(height.saturating_sub(SIZE) as Float);
+        }
+        Some(event)
+    }
+
     fn update(&mut self, dt: Float, eng: &Engine) {
-        if eng.is_key_pressed(Keycode::Left) {
-            self.x -= dt * self.speed;
+        let mut direction = Vec2::ZERO;
+
+        if eng.is_key_pressed(Key::Left) {
+            direction.x -= 1.0;
         }
-        if eng.is_key_pressed(Keycode::Right) {
-            self.x += dt * self.speed;
+        if eng.is_key_pressed(Key::Right) {
+            direction.x += 1.0;
         }
-        if eng.is_key_pressed(Keycode::Up) {
-            self.y -= dt * self.speed;
+        if eng.is_key_pressed(Key::Up) {
+            direction.y -= 1.0;
         }
-        if eng.is_key_pressed(Keycode::Down) {
-            self.y += dt * self.speed;
+        if eng.is_key_pressed(Key::Down) {
+            direction.y += 1.0;
         }
+
+        self.position += direction * dt * self.speed;
     }
 
     fn render(&mut self, engine: &mut Engine) {
-        engine.draw_rect(self.x as i32, self.y as i32, 20, 20, Color::RGB(0, 255, 0));
+        engine.draw_rect(self.position, SIZE, SIZE, Color::rgb(0, 255, 0));
     }
 }
 
 fn main() {
     engine::create(
         BasicGame {
-            x: 0.0,
-            y: 0.0,
+            position: Vec2::ZERO,
             speed: 250.0,
         },
         String::from("Basic Sample"),
@@ -0,0 +1,411 @@
+use crate::{Engine, Float, FrameRect, Point, SpriteSheet};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    /// Plays forward, then backward, then forward again
+    PingPong,
+    /// Stops on the last frame
+    Once,
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationFrame {
+    pub rect: FrameRect,
+    /// Duration in seconds
+    pub duration: Float,
+    /// Name reported when the frame is entered
+    pub event: Option<String>,
+}
+
+/// Sequence of sprite sheet frames
+#[derive(Clone, Debug)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    mode: PlayMode,
+}
+
+impl Animation {
+    pub fn new(mode: PlayMode) -> Animation {
+        Animation {
+            frames: Vec::new(),
+            mode,
+        }
+    }
+
+    /// Animation of `count` equally long frames taken from a grid sheet, starting at cell `first`
+    pub fn from_grid(
+        mode: PlayMode,
+        columns: u32,
+        frame_width: u32,
+        frame_height: u32,
+        first: u32,
+        count: u32,
+        duration: Float,
+    ) -> Animation {
+        (first..first + count).fold(Animation::new(mode), |animation, index| {
+            animation.with_frame(
+                FrameRect::from_grid(index, columns, frame_width, frame_height),
+                duration,
+            )
+        })
+    }
+
+    /// Panics if `duration` is not positive
+    pub fn with_frame(mut self, rect: FrameRect, duration: Float) -> Animation {
+        assert!(duration > 0.0, "Frame duration must be positive");
+        self.frames.push(AnimationFrame {
+            rect,
+            duration,
+            event: None,
+        });
+        self
+    }
+
+    /// Reports `event` whenever frame `index` is entered.
+    /// Panics if there is no frame `index`, so frames have to be added first.
+    pub fn with_event(mut self, index: usize, event: &str) -> Animation {
+        let count = self.frames.len();
+        let frame = self
+            .frames
+            .get_mut(index)
+            .unwrap_or_else(|| panic!("No frame {} in an animation of {} frames", index, count));
+        frame.event = Some(event.to_string());
+        self
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    pub fn mode(&self) -> PlayMode {
+        self.mode
+    }
+
+    /// Total duration of a single pass in seconds
+    pub fn duration(&self) -> Float {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// Playback state of a single animation
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    frame: usize,
+    time: Float,
+    backwards: bool,
+    finished: bool,
+    entered: bool,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        AnimationPlayer {
+            frame: 0,
+            time: 0.0,
+            backwards: false,
+            finished: false,
+            entered: false,
+        }
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new() -> AnimationPlayer {
+        AnimationPlayer::default()
+    }
+
+    pub fn restart(&mut self) {
+        *self = AnimationPlayer::default();
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+
+    /// True once a `PlayMode::Once` animation reached the end of its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn current_frame<'a>(&self, animation: &'a Animation) -> Option<&'a AnimationFrame> {
+        animation.frames.get(self.frame)
+    }
+
+    /// Advances playback by `dt` seconds, calling `on_event` for events of every frame entered
+    pub fn update(&mut self, animation: &Animation, dt: Float, mut on_event: impl FnMut(&str)) {
+        if animation.frames.is_empty() {
+            return;
+        }
+
+        if !self.entered {
+            self.entered = true;
+            fire_event(animation, self.frame, &mut on_event);
+        }
+
+        if self.finished {
+            return;
+        }
+
+        self.time += dt;
+        while self.time >= animation.frames[self.frame].duration {
+            self.time -= animation.frames[self.frame].duration;
+
+            if !self.advance(animation) {
+                self.finished = true;
+                self.time = 0.0;
+                break;
+            }
+
+            fire_event(animation, self.frame, &mut on_event);
+        }
+    }
+
+    /// Moves to the next frame, returns false when a `Once` animation has no more frames
+    fn advance(&mut self, animation: &Animation) -> bool {
+        let count = animation.frames.len();
+
+        match animation.mode {
+            PlayMode::Loop => self.frame = (self.frame + 1) % count,
+            PlayMode::Once => {
+                if self.frame + 1 >= count {
+                    return false;
+                }
+                self.frame += 1;
+            }
+            PlayMode::PingPong => {
+                if count == 1 {
+                    return true;
+                }
+
+                if (self.backwards && self.frame == 0)
+                    || (!self.backwards && self.frame + 1 == count)
+                {
+                    self.backwards = !self.backwards;
+                }
+
+                if self.backwards {
+                    self.frame -= 1;
+                } else {
+                    self.frame += 1;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+fn fire_event(animation: &Animation, frame: usize, on_event: &mut impl FnMut(&str)) {
+    if let Some(event) = &animation.frames[frame].event {
+        on_event(event);
+    }
+}
+
+/// State machine choosing which animation plays.
+///
+/// Each state `S` maps to one animation. Switching state restarts playback, and a state
+/// can hand over to another automatically once its `Once` animation finishes.
+pub struct Animator<S> {
+    animations: HashMap<S, Animation>,
+    on_finish: HashMap<S, S>,
+    state: S,
+    player: AnimationPlayer,
+}
+
+impl<S: Copy + Eq + Hash> Animator<S> {
+    pub fn new(state: S, animation: Animation) -> Animator<S> {
+        Animator {
+            animations: HashMap::from([(state, animation)]),
+            on_finish: HashMap::new(),
+            state,
+            player: AnimationPlayer::new(),
+        }
+    }
+
+    pub fn with_state(mut self, state: S, animation: Animation) -> Animator<S> {
+        self.animations.insert(state, animation);
+        self
+    }
+
+    /// Switches from `from` to `to` when the `from` animation finishes
+    pub fn with_transition_on_finish(mut self, from: S, to: S) -> Animator<S> {
+        self.on_finish.insert(from, to);
+        self
+    }
+
+    pub fn state(&self) -> S {
+        self.state
+    }
+
+    /// Switches to `state`. Playback restarts unless the state is already active.
+    /// Panics if no animation was registered for `state`.
+    pub fn set_state(&mut self, state: S) {
+        if state == self.state {
+            return;
+        }
+
+        assert!(
+            self.animations.contains_key(&state),
+            "No animation for the animator state"
+        );
+        self.state = state;
+        self.player.restart();
+    }
+
+    pub fn player(&self) -> &AnimationPlayer {
+        &self.player
+    }
+
+    pub fn update(&mut self, dt: Float, mut on_event: impl FnMut(&str)) {
+        self.player
+            .update(&self.animations[&self.state], dt, &mut on_event);
+
+        if self.player.is_finished() {
+            if let Some(&next) = self.on_finish.get(&self.state) {
+                self.set_state(next);
+                self.player
+                    .update(&self.animations[&self.state], 0.0, &mut on_event);
+            }
+        }
+    }
+
+    pub fn current_frame(&self) -> Option<&AnimationFrame> {
+        self.player.current_frame(&self.animations[&self.state])
+    }
+
+    pub fn render(&self, engine: &mut Engine, sheet: SpriteSheet, position: impl Into<Point>) {
+        if let Some(frame) = self.current_frame() {
+            engine.draw_sprite(sheet, frame.rect, position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(mode: PlayMode, count: u32) -> Animation {
+        Animation::from_grid(mode, count, 8, 8, 0, count, 1.0)
+    }
+
+    /// Frame index after each of `steps` one second updates
+    fn sequence(animation: &Animation, steps: usize) -> Vec<usize> {
+        let mut player = AnimationPlayer::new();
+        (0..steps)
+            .map(|_| {
+                player.update(animation, 1.0, |_| ());
+                player.frame_index()
+            })
+            .collect()
+    }
+
+    fn events(animation: &Animation, updates: &[Float]) -> Vec<String> {
+        let mut player = AnimationPlayer::new();
+        let mut events = Vec::new();
+        for &dt in updates {
+            player.update(animation, dt, |event| events.push(event.to_string()));
+        }
+        events
+    }
+
+    #[test]
+    fn loops_wrap_around() {
+        let animation = animation(PlayMode::Loop, 3);
+        assert_eq!(sequence(&animation, 5), [1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        let animation = animation(PlayMode::PingPong, 3);
+        assert_eq!(sequence(&animation, 6), [1, 2, 1, 0, 1, 2]);
+        assert_eq!(sequence(&self::animation(PlayMode::PingPong, 1), 2), [0, 0]);
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let animation = animation(PlayMode::Once, 3);
+        let mut player = AnimationPlayer::new();
+
+        player.update(&animation, 2.5, |_| ());
+        assert_eq!(player.frame_index(), 2);
+        assert!(!player.is_finished());
+
+        player.update(&animation, 0.5, |_| ());
+        assert!(player.is_finished());
+        player.update(&animation, 10.0, |_| ());
+        assert_eq!(player.frame_index(), 2);
+    }
+
+    #[test]
+    fn events_fire_once_per_pass() {
+        let animation = animation(PlayMode::Loop, 3)
+            .with_event(0, "start")
+            .with_event(2, "end");
+
+        // Also when a single update skips over several frames
+        assert_eq!(
+            events(&animation, &[0.5, 0.5, 1.0, 1.0]),
+            ["start", "end", "start"]
+        );
+        assert_eq!(
+            events(&animation, &[6.5]),
+            ["start", "end", "start", "end", "start"]
+        );
+
+        let ping_pong = self::animation(PlayMode::PingPong, 3).with_event(1, "middle");
+        assert_eq!(events(&ping_pong, &[1.0, 1.0, 1.0]), ["middle", "middle"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "No frame 3")]
+    fn events_need_an_existing_frame() {
+        let _ = animation(PlayMode::Loop, 3).with_event(3, "missing");
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    enum State {
+        Idle,
+        Attack,
+    }
+
+    fn animator() -> Animator<State> {
+        Animator::new(
+            State::Idle,
+            animation(PlayMode::Loop, 2).with_event(0, "idle"),
+        )
+        .with_state(
+            State::Attack,
+            animation(PlayMode::Once, 2).with_event(1, "hit"),
+        )
+        .with_transition_on_finish(State::Attack, State::Idle)
+    }
+
+    #[test]
+    fn finished_states_hand_over() {
+        let mut animator = animator();
+        let mut events = Vec::new();
+        animator.update(1.0, |event| events.push(event.to_string()));
+        assert_eq!(animator.player().frame_index(), 1);
+
+        animator.set_state(State::Attack);
+        assert_eq!(animator.player().frame_index(), 0);
+        animator.update(1.0, |event| events.push(event.to_string()));
+        assert_eq!(animator.state(), State::Attack);
+
+        animator.update(1.0, |event| events.push(event.to_string()));
+        assert_eq!(animator.state(), State::Idle);
+        assert_eq!(animator.player().frame_index(), 0);
+        assert_eq!(events, ["idle", "hit", "idle"]);
+    }
+
+    #[test]
+    fn setting_the_active_state_keeps_playing() {
+        let mut animator = animator();
+        animator.update(1.0, |_| ());
+        animator.set_state(State::Idle);
+        assert_eq!(animator.player().frame_index(), 1);
+    }
+}
@@ -0,0 +1,434 @@
+//! Asset registry with typed handles.
+//!
+//! Assets are loaded by path relative to the asset root and cached, so loading the same
+//! path twice returns the same handle. On desktop the files are watched and changed assets
+//! are reloaded in place between frames. Reload problems, e.g. a changed file that no
+//! longer loads, are published as `AssetError` events, see `Engine::subscribe`.
+//!
+//! On the web the files are read from the Emscripten virtual file system, which
+//! `build_wasm.py` fills with the project's `assets` directory.
+
+use crate::{Engine, SpriteSheet};
+use sdl2::render::{BlendMode, Texture, WindowCanvas};
+use sdl2::surface::Surface;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// Typed reference to a loaded asset
+pub struct Handle<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({})", self.index)
+    }
+}
+
+#[derive(Debug)]
+pub struct AssetError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Could not load {}: {}",
+            self.path.display(),
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+/// Engine resources available while loading an asset
+pub struct LoadContext<'a> {
+    pub(crate) canvas: &'a mut WindowCanvas,
+    pub(crate) textures: &'a mut Vec<Option<Texture>>,
+}
+
+/// Type that can be loaded from a file through `Engine::load`
+pub trait Asset: Sized + 'static {
+    fn load(path: &Path, context: &mut LoadContext) -> Result<Self, String>;
+
+    /// Replaces `self` with a freshly loaded version of the file.
+    /// Implementations can override it to keep derived handles valid.
+    fn reload(&mut self, reloaded: Self, _context: &mut LoadContext) {
+        *self = reloaded;
+    }
+}
+
+/// Image loaded into a sprite sheet.
+/// BMP is always supported, other formats need the `image` feature.
+pub struct Image {
+    pub sheet: SpriteSheet,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Asset for Image {
+    fn load(path: &Path, context: &mut LoadContext) -> Result<Self, String> {
+        let mut texture = load_texture(path, context.canvas)?;
+        texture.set_blend_mode(BlendMode::Blend);
+
+        let query = texture.query();
+        context.textures.push(Some(texture));
+
+        Ok(Image {
+            sheet: SpriteSheet(context.textures.len() - 1),
+            width: query.width,
+            height: query.height,
+        })
+    }
+
+    fn reload(&mut self, reloaded: Self, context: &mut LoadContext) {
+        // Move the new texture into the old slot so the sprite sheet stays valid. It was
+        // loaded just before, so its own slot is the last one and can be removed.
+        debug_assert_eq!(reloaded.sheet.0, context.textures.len() - 1);
+        let texture = context.textures.pop().flatten();
+        let old = std::mem::replace(&mut context.textures[self.sheet.0], texture);
+        if let Some(old) = old {
+            // SAFETY: the texture was created by the engine's canvas, which is still alive
+            unsafe { old.destroy() };
+        }
+
+        self.width = reloaded.width;
+        self.height = reloaded.height;
+    }
+}
+
+#[cfg(feature = "image")]
+fn load_texture(path: &Path, canvas: &mut WindowCanvas) -> Result<Texture, String> {
+    use sdl2::image::LoadSurface;
+
+    let surface = Surface::from_file(path)?;
+    canvas
+        .create_texture_from_surface(&surface)
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(not(feature = "image"))]
+fn load_texture(path: &Path, canvas: &mut WindowCanvas) -> Result<Texture, String> {
+    let surface = Surface::load_bmp(path)?;
+    canvas
+        .create_texture_from_surface(&surface)
+        .map_err(|error| error.to_string())
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    U8,
+    S8,
+    U16,
+    S16,
+    S32,
+    F32,
+}
+
+/// Decoded WAV file. Multi-byte samples are little-endian and channels are interleaved.
+pub struct Sound {
+    pub frequency: i32,
+    pub channels: u8,
+    pub format: SampleFormat,
+    pub data: Vec<u8>,
+}
+
+impl Asset for Sound {
+    fn load(path: &Path, _context: &mut LoadContext) -> Result<Self, String> {
+        use sdl2::audio::{AudioFormat, AudioSpecWAV};
+
+        let wav = AudioSpecWAV::load_wav(path)?;
+        let format = match wav.format {
+            AudioFormat::U8 => SampleFormat::U8,
+            AudioFormat::S8 => SampleFormat::S8,
+            AudioFormat::U16LSB => SampleFormat::U16,
+            AudioFormat::S16LSB => SampleFormat::S16,
+            AudioFormat::S32LSB => SampleFormat::S32,
+            AudioFormat::F32LSB => SampleFormat::F32,
+            format => return Err(format!("Unsupported sample format {:?}", format)),
+        };
+
+        Ok(Sound {
+            frequency: wav.freq,
+            channels: wav.channels,
+            format,
+            data: wav.buffer().to_vec(),
+        })
+    }
+}
+
+/// Raw font file contents, e.g. a TTF
+pub struct Font {
+    pub data: Vec<u8>,
+}
+
+impl Asset for Font {
+    fn load(path: &Path, _context: &mut LoadContext) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|error| error.to_string())?;
+        Ok(Font { data })
+    }
+}
+
+/// Text or configuration file
+impl Asset for String {
+    fn load(path: &Path, _context: &mut LoadContext) -> Result<Self, String> {
+        std::fs::read_to_string(path).map_err(|error| error.to_string())
+    }
+}
+
+struct Entry<T> {
+    path: PathBuf,
+    asset: T,
+}
+
+trait AnyStorage {
+    fn reload(&mut self, path: &Path, context: &mut LoadContext) -> Result<(), String>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Asset> AnyStorage for Vec<Entry<T>> {
+    fn reload(&mut self, path: &Path, context: &mut LoadContext) -> Result<(), String> {
+        for entry in self.iter_mut().filter(|entry| entry.path == path) {
+            // On errors the old version is kept, the file may still be mid-write
+            let reloaded = T::load(path, context)?;
+            entry.asset.reload(reloaded, context);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub(crate) struct Assets {
+    root: PathBuf,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    handles: HashMap<(TypeId, PathBuf), usize>,
+    #[cfg(not(target_family = "wasm"))]
+    watcher: Option<watch::Watcher>,
+    /// Hot reload problems waiting to be published
+    #[cfg(not(target_family = "wasm"))]
+    errors: Vec<AssetError>,
+}
+
+impl Assets {
+    pub(crate) fn new(root: PathBuf) -> Assets {
+        #[cfg(not(target_family = "wasm"))]
+        let (watcher, errors) = match watch::Watcher::new() {
+            Ok(watcher) => (Some(watcher), Vec::new()),
+            Err(message) => {
+                let error = AssetError {
+                    path: root.clone(),
+                    message: format!("hot reload is disabled ({})", message),
+                };
+                (None, vec![error])
+            }
+        };
+
+        Assets {
+            root,
+            storages: HashMap::new(),
+            handles: HashMap::new(),
+            #[cfg(not(target_family = "wasm"))]
+            watcher,
+            #[cfg(not(target_family = "wasm"))]
+            errors,
+        }
+    }
+
+    pub(crate) fn load<T: Asset>(
+        &mut self,
+        path: &Path,
+        context: &mut LoadContext,
+    ) -> Result<Handle<T>, AssetError> {
+        let path = self.resolve(path);
+        let key = (TypeId::of::<T>(), path.clone());
+        if let Some(&index) = self.handles.get(&key) {
+            return Ok(Handle {
+                index,
+                marker: PhantomData,
+            });
+        }
+
+        let asset = T::load(&path, context).map_err(|message| AssetError {
+            path: path.clone(),
+            message,
+        })?;
+
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(watcher) = &mut self.watcher {
+            if let Err(error) = watcher.watch(&path) {
+                self.errors.push(error);
+            }
+        }
+
+        let entries = self
+            .storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Vec::<Entry<T>>::new()))
+            .as_any_mut()
+            .downcast_mut::<Vec<Entry<T>>>()
+            .unwrap();
+        entries.push(Entry { path, asset });
+
+        let index = entries.len() - 1;
+        self.handles.insert(key, index);
+        Ok(Handle {
+            index,
+            marker: PhantomData,
+        })
+    }
+
+    pub(crate) fn get<T: Asset>(&self, handle: Handle<T>) -> &T {
+        let entries = self.storages[&TypeId::of::<T>()]
+            .as_any()
+            .downcast_ref::<Vec<Entry<T>>>()
+            .unwrap();
+        &entries[handle.index].asset
+    }
+
+    /// Reloads assets whose files changed since the last call
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn reload_changed(&mut self, context: &mut LoadContext) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+
+        for path in watcher.changed_paths() {
+            for storage in self.storages.values_mut() {
+                if let Err(message) = storage.reload(&path, context) {
+                    self.errors.push(AssetError {
+                        path: path.clone(),
+                        message,
+                    });
+                }
+            }
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn take_errors(&mut self) -> Vec<AssetError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        let path = self.root.join(path);
+        // Canonical paths let watcher events be matched against loaded assets
+        std::fs::canonicalize(&path).unwrap_or(path)
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+mod watch {
+    use super::AssetError;
+    use notify::{EventKind, RecursiveMode, Watcher as _};
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{channel, Receiver};
+
+    pub(super) struct Watcher {
+        watcher: notify::RecommendedWatcher,
+        events: Receiver<notify::Result<notify::Event>>,
+        directories: HashSet<PathBuf>,
+    }
+
+    impl Watcher {
+        pub(super) fn new() -> Result<Watcher, String> {
+            let (sender, events) = channel();
+            let watcher = notify::recommended_watcher(sender).map_err(|error| error.to_string())?;
+            Ok(Watcher {
+                watcher,
+                events,
+                directories: HashSet::new(),
+            })
+        }
+
+        pub(super) fn watch(&mut self, path: &Path) -> Result<(), AssetError> {
+            // Watch the directory, editors often save by replacing the file
+            let Some(directory) = path.parent() else {
+                return Ok(());
+            };
+
+            if self.directories.insert(directory.to_path_buf()) {
+                self.watcher
+                    .watch(directory, RecursiveMode::NonRecursive)
+                    .map_err(|error| AssetError {
+                        path: directory.to_path_buf(),
+                        message: format!("changes won't be reloaded ({})", error),
+                    })?;
+            }
+            Ok(())
+        }
+
+        /// Paths modified since the last call, each reported once
+        pub(super) fn changed_paths(&self) -> HashSet<PathBuf> {
+            self.events
+                .try_iter()
+                .filter_map(Result::ok)
+                .filter(|event| matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)))
+                .flat_map(|event| event.paths)
+                .map(|path| std::fs::canonicalize(&path).unwrap_or(path))
+                .collect()
+        }
+    }
+}
+
+// Asset API
+impl Engine {
+    /// Loads an asset, or returns the cached handle if the path was loaded before.
+    /// Relative paths are resolved against the asset root set in the `EngineBuilder`.
+    pub fn load<T: Asset>(&mut self, path: impl AsRef<Path>) -> Result<Handle<T>, AssetError> {
+        let mut context = LoadContext {
+            canvas: &mut self.canvas,
+            textures: &mut self.textures,
+        };
+        self.assets.load(path.as_ref(), &mut context)
+    }
+
+    pub fn asset<T: Asset>(&self, handle: Handle<T>) -> &T {
+        self.assets.get(handle)
+    }
+
+    /// Also publishes the hot reload problems since the last frame as `AssetError` events
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn reload_changed_assets(&mut self) {
+        let mut context = LoadContext {
+            canvas: &mut self.canvas,
+            textures: &mut self.textures,
+        };
+        self.assets.reload_changed(&mut context);
+
+        for error in self.assets.take_errors() {
+            self.publish(error);
+        }
+    }
+}
@@ -0,0 +1,393 @@
+//! Engine settings read from a TOML file and overridden from the command line.
+//!
+//! ```toml
+//! title = "Maze"
+//! scaling = "fit"        # "fixed", "fit" or "stretch"
+//! width = 161            # in points
+//! height = 81
+//! target_fps = 60
+//! time_scale = 0.25      # slow motion
+//! vsync = false
+//! fullscreen = false
+//! display = 1            # second monitor
+//! borderless = false
+//! always_on_top = false
+//! remember_window = true # reopen where the window was closed
+//!
+//! [bindings]             # "pause" and "step" default to Pause and F10
+//! step = ["Period", "Right"]
+//!
+//! [game]                 # free-form settings read with `EngineConfig::game`
+//! path_width = 3
+//! ```
+//!
+//! Supported flags: `--config <path>`, `--title <text>`, `--width <points>`, `--height <points>`,
+//! `--scale <point size>`, `--scaling <mode>`, `--fps <fps>`, `--time-scale <factor>`, `--paused`,
+//! `--vsync`, `--fullscreen`, `--windowed`, `--display <index>`, `--borderless`, `--always-on-top`,
+//! `--remember-window`, `--seed <number>`, `--headless`
+//! and `--set <key>=<value>` for `[game]` values.
+
+use crate::{Float, Key};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScalingMode {
+    /// `scale` pixels per point, `width` x `height` points
+    Fixed,
+    /// `width` x `height` points with the largest point size fitting the screen
+    Fit,
+    /// Points of size `scale` covering the whole screen
+    Stretch,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    pub title: Option<String>,
+    pub scaling: Option<ScalingMode>,
+    /// Point size in pixels
+    pub scale: Option<u32>,
+    /// Width in points
+    pub width: Option<u32>,
+    /// Height in points
+    pub height: Option<u32>,
+    pub target_fps: u32,
+    /// Multiplier applied to `dt`, below 1 for slow motion
+    pub time_scale: Float,
+    /// Starts with updates paused, see `Engine::set_paused`
+    pub paused: bool,
+    pub vsync: bool,
+    pub fullscreen: bool,
+    /// Index of the display the window opens on and whose size `Fit` and `Stretch` use
+    pub display: u32,
+    /// Window without title bar and frame
+    pub borderless: bool,
+    pub always_on_top: bool,
+    /// Stores the window position and size on exit and restores them on the next run.
+    /// The size is only restored with `Stretch` scaling, otherwise it follows from the points.
+    /// Failing to store them publishes a `SaveError`.
+    pub remember_window: bool,
+    /// Seed for the game's random number generators, random when not set
+    pub seed: Option<u64>,
+    /// Runs without showing a window, e.g. for automated runs on CI
+    pub headless: bool,
+    /// Named actions and the keys triggering them, see `Engine::is_action_pressed`
+    pub bindings: HashMap<String, Vec<Key>>,
+    /// Game specific settings
+    pub game: toml::Table,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            title: None,
+            scaling: None,
+            scale: None,
+            width: None,
+            height: None,
+            target_fps: 60,
+            time_scale: 1.0,
+            paused: false,
+            vsync: false,
+            fullscreen: false,
+            display: 0,
+            borderless: false,
+            always_on_top: false,
+            remember_window: false,
+            seed: None,
+            headless: false,
+            bindings: HashMap::new(),
+            game: toml::Table::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Toml(toml::de::Error),
+    /// Unknown or malformed command-line argument
+    Argument(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => {
+                write!(f, "Could not read {}: {}", path.display(), error)
+            }
+            ConfigError::Toml(error) => write!(f, "Invalid config: {}", error),
+            ConfigError::Argument(argument) => write!(f, "Invalid argument: {}", argument),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl EngineConfig {
+    pub fn from_toml(source: &str) -> Result<EngineConfig, ConfigError> {
+        let config: EngineConfig = toml::from_str(source).map_err(ConfigError::Toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<EngineConfig, ConfigError> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|error| ConfigError::Io(path.into(), error))?;
+        EngineConfig::from_toml(&source)
+    }
+
+    /// Reads `default_path`, or the file given with `--config`, and applies the process arguments.
+    /// A missing file at `default_path` is not an error, the defaults are used instead.
+    pub fn load_with_args(default_path: impl AsRef<Path>) -> Result<EngineConfig, ConfigError> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+
+        let mut config = match config_path(&args)? {
+            Some(path) => EngineConfig::load(path)?,
+            None if default_path.as_ref().exists() => EngineConfig::load(default_path)?,
+            None => EngineConfig::default(),
+        };
+
+        config.apply_args(args)?;
+        Ok(config)
+    }
+
+    /// Overrides settings with command-line flags. Values can follow the flag or an `=`.
+    pub fn apply_args(
+        &mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(), ConfigError> {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ConfigError::Argument(format!("{} needs a value", flag)))
+            };
+
+            match flag.as_str() {
+                "--config" => {
+                    // Already used by `load_with_args`
+                    value()?;
+                }
+                "--title" => self.title = Some(value()?),
+                "--width" => self.width = Some(parse(&flag, &value()?)?),
+                "--height" => self.height = Some(parse(&flag, &value()?)?),
+                "--scale" => self.scale = Some(parse(&flag, &value()?)?),
+                "--scaling" => {
+                    let mode = value()?;
+                    self.scaling = Some(
+                        ScalingMode::deserialize(toml::Value::String(mode.clone()))
+                            .map_err(|_| ConfigError::Argument(format!("--scaling {}", mode)))?,
+                    );
+                }
+                "--fps" => self.target_fps = parse(&flag, &value()?)?,
+                "--time-scale" => self.time_scale = parse(&flag, &value()?)?,
+                "--paused" => self.paused = true,
+                "--seed" => self.seed = Some(parse(&flag, &value()?)?),
+                "--vsync" => self.vsync = true,
+                "--fullscreen" => self.fullscreen = true,
+                "--windowed" => self.fullscreen = false,
+                "--display" => self.display = parse(&flag, &value()?)?,
+                "--borderless" => self.borderless = true,
+                "--always-on-top" => self.always_on_top = true,
+                "--remember-window" => self.remember_window = true,
+                "--headless" => self.headless = true,
+                "--set" => {
+                    let setting = value()?;
+                    let Some((key, raw)) = setting.split_once('=') else {
+                        return Err(ConfigError::Argument(format!("--set {}", setting)));
+                    };
+                    self.game.insert(key.to_string(), parse_value(raw));
+                }
+                _ => return Err(ConfigError::Argument(arg)),
+            }
+        }
+
+        self.validate()
+    }
+
+    /// Rejects values the engine can't start with, e.g. from `--scale 0`
+    fn validate(&self) -> Result<(), ConfigError> {
+        let zero = [
+            ("width", self.width),
+            ("height", self.height),
+            ("scale", self.scale),
+        ]
+        .into_iter()
+        .find(|&(_, value)| value == Some(0));
+        if let Some((name, _)) = zero {
+            return Err(ConfigError::Argument(format!(
+                "{} must be at least 1",
+                name
+            )));
+        }
+        if !(self.time_scale > 0.0 && self.time_scale.is_finite()) {
+            return Err(ConfigError::Argument(format!(
+                "time_scale must be positive, got {}",
+                self.time_scale
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Deserializes the `[game]` table into the game's own settings type
+    pub fn game<T: DeserializeOwned>(&self) -> Result<T, ConfigError> {
+        toml::Value::Table(self.game.clone())
+            .try_into()
+            .map_err(ConfigError::Toml)
+    }
+}
+
+/// Path given with `--config <path>` or `--config=<path>`
+fn config_path(args: &[String]) -> Result<Option<PathBuf>, ConfigError> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.split_once('=') {
+            Some(("--config", path)) => return Ok(Some(PathBuf::from(path))),
+            _ if arg == "--config" => {
+                return args
+                    .next()
+                    .map(|path| Some(PathBuf::from(path)))
+                    .ok_or_else(|| ConfigError::Argument("--config needs a value".to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(None)
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::Argument(format!("{} {}", flag, value)))
+}
+
+/// Reads `--set` values as TOML, so numbers and booleans keep their type.
+/// Anything else is taken as a plain string.
+fn parse_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Key::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown key \"{}\"", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn apply(arguments: &[&str]) -> Result<EngineConfig, ConfigError> {
+        let mut config = EngineConfig::default();
+        config.apply_args(args(arguments))?;
+        Ok(config)
+    }
+
+    fn is_argument_error(result: Result<EngineConfig, ConfigError>) -> bool {
+        matches!(result, Err(ConfigError::Argument(_)))
+    }
+
+    #[test]
+    fn values_follow_the_flag_or_an_equals_sign() {
+        let config = apply(&["--width", "40", "--height=30", "--title=A = B", "--paused"]).unwrap();
+        assert_eq!(config.width, Some(40));
+        assert_eq!(config.height, Some(30));
+        assert_eq!(config.title.as_deref(), Some("A = B"));
+        assert!(config.paused);
+    }
+
+    #[test]
+    fn config_paths_are_found_in_both_forms() {
+        let path = |arguments: &[&str]| config_path(&args(arguments)).unwrap();
+        assert_eq!(path(&["--config", "a.toml"]), Some(PathBuf::from("a.toml")));
+        assert_eq!(
+            path(&["--paused", "--config=b.toml"]),
+            Some(PathBuf::from("b.toml"))
+        );
+        assert_eq!(path(&["--paused"]), None);
+        assert!(config_path(&args(&["--config"])).is_err());
+
+        // Skipped when the remaining arguments are applied
+        let config = apply(&["--config=b.toml", "--config", "a.toml", "--fps", "30"]).unwrap();
+        assert_eq!(config.target_fps, 30);
+    }
+
+    #[test]
+    fn set_values_keep_their_type() {
+        let config = apply(&[
+            "--set",
+            "rows=20",
+            "--set=rate=1.5",
+            "--set",
+            "debug=true",
+            "--set",
+            "name=maze",
+        ])
+        .unwrap();
+        assert_eq!(config.game["rows"], toml::Value::Integer(20));
+        assert_eq!(config.game["rate"], toml::Value::Float(1.5));
+        assert_eq!(config.game["debug"], toml::Value::Boolean(true));
+        assert_eq!(config.game["name"], toml::Value::String("maze".to_string()));
+
+        assert!(is_argument_error(apply(&["--set", "rows"])));
+    }
+
+    #[test]
+    fn malformed_arguments_are_errors() {
+        assert!(is_argument_error(apply(&["--width"])));
+        assert!(is_argument_error(apply(&["--width", "wide"])));
+        assert!(is_argument_error(apply(&["--unknown"])));
+        assert!(is_argument_error(apply(&["--scaling", "zoom"])));
+
+        let config = apply(&["--scaling", "stretch"]).unwrap();
+        assert_eq!(config.scaling, Some(ScalingMode::Stretch));
+    }
+
+    #[test]
+    fn sizes_and_time_scale_must_be_positive() {
+        for arguments in [
+            ["--width", "0"],
+            ["--height", "0"],
+            ["--scale", "0"],
+            ["--time-scale", "0"],
+            ["--time-scale", "-1"],
+            ["--time-scale", "NaN"],
+        ] {
+            assert!(is_argument_error(apply(&arguments)), "{:?}", arguments);
+        }
+
+        assert!(matches!(
+            EngineConfig::from_toml("width = 0"),
+            Err(ConfigError::Argument(_))
+        ));
+        assert!(EngineConfig::from_toml("width = 10\ntime_scale = 0.5").is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{Color, Engine, Point};
+use sdl2::mouse::{Cursor, SystemCursor};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+
+// Cursor API
+impl Engine {
+    pub fn is_cursor_visible(&self) -> bool {
+        self.sdl.mouse().is_cursor_showing()
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.sdl.mouse().show_cursor(visible);
+    }
+
+    /// Replaces the system cursor with an image given row by row, like
+    /// `create_sprite_sheet`. Every pixel covers one point and `hot_spot` is the pixel
+    /// that clicks land on.
+    pub fn set_cursor(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: &[Color],
+        hot_spot: impl Into<Point>,
+    ) -> Result<(), String> {
+        assert_eq!(
+            pixels.len(),
+            (width * height) as usize,
+            "Pixel count doesn't match the size"
+        );
+
+        let image = CursorImage {
+            width,
+            height,
+            pixels: pixels.to_vec(),
+            hot_spot: hot_spot.into(),
+        };
+        let cursor = image.create(self.dimensions.point_size())?;
+        cursor.set();
+
+        // SDL keeps using the cursor, so it has to live until it's replaced
+        self.cursor = Some(cursor);
+        self.cursor_image = Some(image);
+        Ok(())
+    }
+
+    /// Goes back to the system's arrow cursor
+    pub fn reset_cursor(&mut self) {
+        let cursor = Cursor::from_system(SystemCursor::Arrow).unwrap();
+        cursor.set();
+        self.cursor = Some(cursor);
+        self.cursor_image = None;
+    }
+
+    /// Scales the image given to `set_cursor` again after the point size changed.
+    /// The old cursor stays if the new one can't be created.
+    #[cfg(target_family = "wasm")]
+    pub(crate) fn rescale_cursor(&mut self) {
+        let point_size = self.dimensions.point_size();
+        if let Some(Ok(cursor)) = self
+            .cursor_image
+            .as_ref()
+            .map(|image| image.create(point_size))
+        {
+            cursor.set();
+            self.cursor = Some(cursor);
+        }
+    }
+
+    pub fn is_relative_mouse_mode(&self) -> bool {
+        #[cfg(target_family = "wasm")]
+        return crate::emscripten::is_pointer_locked();
+
+        #[cfg(not(target_family = "wasm"))]
+        return self.sdl.mouse().relative_mouse_mode();
+    }
+
+    /// Hides the cursor and keeps reporting `Event::MouseMotion` deltas while the mouse
+    /// keeps moving in one direction, e.g. for mouse look or drag-to-pan.
+    ///
+    /// On the web this locks the pointer to the canvas. Browsers only allow that after a
+    /// click or key press, so the lock may start with the next one, and they end it on
+    /// their own when Escape is pressed, which `is_relative_mouse_mode` reflects.
+    pub fn set_relative_mouse_mode(&mut self, relative: bool) {
+        self.sdl.mouse().set_relative_mouse_mode(relative);
+
+        #[cfg(target_family = "wasm")]
+        if relative {
+            crate::emscripten::request_pointer_lock();
+        } else {
+            crate::emscripten::exit_pointer_lock();
+        }
+    }
+}
+
+/// Image given to `set_cursor`, kept to scale it again when the point size changes
+pub(crate) struct CursorImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    hot_spot: Point,
+}
+
+impl CursorImage {
+    /// Scaled up front, the cursor is drawn by the system at its pixel size
+    fn create(&self, point_size: u32) -> Result<Cursor, String> {
+        let (width, height) = (self.width * point_size, self.height * point_size);
+        let mut bytes = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let color = self.pixels[((y / point_size) * self.width + x / point_size) as usize];
+                bytes.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+        }
+
+        let surface = Surface::from_data(
+            &mut bytes,
+            width,
+            height,
+            width * 4,
+            PixelFormatEnum::RGBA32,
+        )?;
+        let hot_spot = self.hot_spot * point_size as i32;
+        Cursor::from_surface(surface, hot_spot.x, hot_spot.y)
+    }
+}
@@ -1,14 +1,18 @@
+use crate::config::ScalingMode;
+use crate::{Event, Float, Point};
+
 #[derive(Debug)]
 pub struct Dimensions {
     point_size: u32,
     width: u32,
     height: u32,
+    pixel_ratio: Float,
 }
 
-pub fn point_at(dimensions: &Dimensions, x: i32, y: i32) -> sdl2::rect::Rect {
+pub fn point_at(dimensions: &Dimensions, position: Point) -> sdl2::rect::Rect {
     sdl2::rect::Rect::new(
-        x * dimensions.point_size() as i32,
-        y * dimensions.point_size() as i32,
+        position.x * dimensions.point_size() as i32,
+        position.y * dimensions.point_size() as i32,
         dimensions.point_size(),
         dimensions.point_size(),
     )
@@ -16,14 +20,13 @@ pub fn point_at(dimensions: &Dimensions, x: i32, y: i32) -> sdl2::rect::Rect {
 
 pub fn rect_at(
     dimensions: &Dimensions,
-    x: i32,
-    y: i32,
+    position: Point,
     width: u32,
     height: u32,
 ) -> sdl2::rect::Rect {
     sdl2::rect::Rect::new(
-        x * dimensions.point_size() as i32,
-        y * dimensions.point_size() as i32,
+        position.x * dimensions.point_size() as i32,
+        position.y * dimensions.point_size() as i32,
         width * dimensions.point_size(),
         height * dimensions.point_size(),
     )
@@ -35,6 +38,7 @@ impl Dimensions {
             width,
             height,
             point_size,
+            pixel_ratio: 1.0,
         }
     }
 
@@ -62,6 +66,84 @@ impl Dimensions {
     pub fn pixel_height(&self) -> u32 {
         self.height * self.point_size
     }
+
+    /// Pixels per window coordinate unit, e.g. 2 on HiDPI displays where the window is
+    /// sized and the mouse reported in units covering several pixels
+    pub fn pixel_ratio(&self) -> Float {
+        self.pixel_ratio
+    }
+
+    pub(crate) fn set_pixel_ratio(&mut self, pixel_ratio: Float) {
+        self.pixel_ratio = pixel_ratio;
+    }
+
+    /// Window width in window coordinates
+    pub fn window_width(&self) -> u32 {
+        (self.pixel_width() as Float / self.pixel_ratio).ceil() as u32
+    }
+
+    /// Window height in window coordinates
+    pub fn window_height(&self) -> u32 {
+        (self.pixel_height() as Float / self.pixel_ratio).ceil() as u32
+    }
+
+    /// Dimensions filling `pixel_width` x `pixel_height` pixels. Fit keeps the number of
+    /// points and picks the largest point size, Stretch keeps the point size and adds or
+    /// removes points, and Fixed stays as it is.
+    pub fn resized(&self, scaling: ScalingMode, pixel_width: u32, pixel_height: u32) -> Dimensions {
+        let (point_size, width, height) = match scaling {
+            ScalingMode::Fixed => (self.point_size, self.width, self.height),
+            ScalingMode::Fit => {
+                let point_size =
+                    (pixel_width / self.width.max(1)).min(pixel_height / self.height.max(1));
+                (point_size.max(1), self.width, self.height)
+            }
+            ScalingMode::Stretch => (
+                self.point_size,
+                (pixel_width / self.point_size.max(1)).max(1),
+                (pixel_height / self.point_size.max(1)).max(1),
+            ),
+        };
+
+        Dimensions {
+            point_size,
+            width,
+            height,
+            pixel_ratio: self.pixel_ratio,
+        }
+    }
+
+    /// `resized` for a browser window of `pixel_width` x `pixel_height` pixels at
+    /// `pixel_ratio`, which changes when the window moves to a screen with a different
+    /// density. Also returns the `Event::Resized` to pass on if anything changed.
+    pub fn resize(
+        &self,
+        scaling: ScalingMode,
+        pixel_width: u32,
+        pixel_height: u32,
+        pixel_ratio: Float,
+    ) -> (Dimensions, Option<Event>) {
+        let mut dimensions = self.resized(scaling, pixel_width, pixel_height);
+        dimensions.pixel_ratio = pixel_ratio;
+
+        let changed = dimensions.point_size != self.point_size
+            || dimensions.width != self.width
+            || dimensions.height != self.height
+            || dimensions.pixel_ratio != self.pixel_ratio;
+        let event = changed.then_some(Event::Resized {
+            width: dimensions.width,
+            height: dimensions.height,
+        });
+        (dimensions, event)
+    }
+
+    /// Converts a position in window coordinates, e.g. of the mouse, to pixels
+    pub(crate) fn to_pixels(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            (x as Float * self.pixel_ratio) as i32,
+            (y as Float * self.pixel_ratio) as i32,
+        )
+    }
 }
 
 impl Default for Dimensions {
@@ -69,3 +151,74 @@ impl Default for Dimensions {
         Dimensions::new(1, 800, 600)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(dimensions: &Dimensions) -> (u32, u32, u32) {
+        (
+            dimensions.point_size(),
+            dimensions.point_width(),
+            dimensions.point_height(),
+        )
+    }
+
+    fn is_resized(event: Option<Event>, width: u32, height: u32) -> bool {
+        matches!(event, Some(Event::Resized { width: w, height: h }) if (w, h) == (width, height))
+    }
+
+    #[test]
+    fn fit_keeps_the_points_and_picks_the_largest_point_size() {
+        let dimensions = Dimensions::new(2, 100, 50);
+
+        let (resized, event) = dimensions.resize(ScalingMode::Fit, 450, 170, 1.0);
+        assert_eq!(sizes(&resized), (3, 100, 50));
+        assert!(is_resized(event, 100, 50));
+
+        // Never smaller than one pixel per point
+        let (resized, _) = dimensions.resize(ScalingMode::Fit, 10, 10, 1.0);
+        assert_eq!(sizes(&resized), (1, 100, 50));
+    }
+
+    #[test]
+    fn stretch_keeps_the_point_size_and_fills_the_window() {
+        let dimensions = Dimensions::new(4, 10, 10);
+
+        let (resized, event) = dimensions.resize(ScalingMode::Stretch, 803, 401, 1.0);
+        assert_eq!(sizes(&resized), (4, 200, 100));
+        assert!(is_resized(event, 200, 100));
+
+        let (resized, _) = dimensions.resize(ScalingMode::Stretch, 0, 0, 1.0);
+        assert_eq!(sizes(&resized), (4, 1, 1));
+    }
+
+    #[test]
+    fn fixed_stays_as_it_is() {
+        let dimensions = Dimensions::new(2, 100, 50);
+
+        let (resized, event) = dimensions.resize(ScalingMode::Fixed, 1000, 1000, 1.0);
+        assert_eq!(sizes(&resized), (2, 100, 50));
+        assert!(event.is_none());
+    }
+
+    #[test]
+    fn unchanged_sizes_send_no_event() {
+        let dimensions = Dimensions::new(3, 100, 50);
+
+        let (resized, event) = dimensions.resize(ScalingMode::Fit, 320, 160, 1.0);
+        assert_eq!(sizes(&resized), (3, 100, 50));
+        assert!(event.is_none());
+    }
+
+    #[test]
+    fn ratio_changes_send_an_event() {
+        let dimensions = Dimensions::new(2, 100, 50);
+
+        let (resized, event) = dimensions.resize(ScalingMode::Fit, 200, 100, 2.0);
+        assert_eq!(sizes(&resized), (2, 100, 50));
+        assert_eq!(resized.pixel_ratio(), 2.0);
+        assert_eq!((resized.window_width(), resized.window_height()), (100, 50));
+        assert!(is_resized(event, 100, 50));
+    }
+}
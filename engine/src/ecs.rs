@@ -0,0 +1,79 @@
+//! Minimal entity-component storage with systems run from `Game::update` and
+//! `Game::render`. The storage itself lives in `world` and doesn't touch the engine.
+
+mod world;
+
+pub use world::{Entity, Query, Storage, World};
+
+use crate::{Engine, Float, Game};
+
+type UpdateSystem = Box<dyn FnMut(&mut World, Float, &Engine)>;
+type RenderSystem = Box<dyn FnMut(&mut World, &mut Engine)>;
+
+/// Systems run in ascending `order`. Systems with the same order run in the order they were added.
+#[derive(Default)]
+pub struct Schedule {
+    update_systems: Vec<(i32, UpdateSystem)>,
+    render_systems: Vec<(i32, RenderSystem)>,
+}
+
+impl Schedule {
+    pub fn new() -> Schedule {
+        Schedule::default()
+    }
+
+    pub fn add_update_system<F>(&mut self, order: i32, system: F) -> &mut Schedule
+    where
+        F: FnMut(&mut World, Float, &Engine) + 'static,
+    {
+        let position = self.update_systems.partition_point(|(o, _)| *o <= order);
+        self.update_systems
+            .insert(position, (order, Box::new(system)));
+        self
+    }
+
+    pub fn add_render_system<F>(&mut self, order: i32, system: F) -> &mut Schedule
+    where
+        F: FnMut(&mut World, &mut Engine) + 'static,
+    {
+        let position = self.render_systems.partition_point(|(o, _)| *o <= order);
+        self.render_systems
+            .insert(position, (order, Box::new(system)));
+        self
+    }
+
+    pub fn run_update(&mut self, world: &mut World, dt: Float, engine: &Engine) {
+        for (_, system) in &mut self.update_systems {
+            system(world, dt, engine);
+        }
+    }
+
+    pub fn run_render(&mut self, world: &mut World, engine: &mut Engine) {
+        for (_, system) in &mut self.render_systems {
+            system(world, engine);
+        }
+    }
+}
+
+/// `Game` that runs a schedule over a world
+#[derive(Default)]
+pub struct EcsGame {
+    pub world: World,
+    pub schedule: Schedule,
+}
+
+impl EcsGame {
+    pub fn new(world: World, schedule: Schedule) -> EcsGame {
+        EcsGame { world, schedule }
+    }
+}
+
+impl Game for EcsGame {
+    fn update(&mut self, dt: Float, engine: &Engine) {
+        self.schedule.run_update(&mut self.world, dt, engine);
+    }
+
+    fn render(&mut self, engine: &mut Engine) {
+        self.schedule.run_render(&mut self.world, engine);
+    }
+}
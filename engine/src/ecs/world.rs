@@ -0,0 +1,416 @@
+//! Entities, component storage and queries, independent of the engine.
+//!
+//! Components live in one sparse vector per type, indexed by entity slot.
+//! Queries borrow each storage through a `RefCell`, so naming the same component
+//! type twice in one query panics.
+
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+
+/// Entity handle. Slots are reused after `despawn`, the generation tells old handles apart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+/// Components of a single type, indexed by entity slot
+pub struct Storage<T> {
+    components: Vec<Option<T>>,
+}
+
+trait AnyStorage {
+    fn remove(&mut self, index: usize);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+    fn remove(&mut self, index: usize) {
+        if let Some(component) = self.components.get_mut(index) {
+            *component = None;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_slots: Vec<u32>,
+    storages: HashMap<TypeId, RefCell<Box<dyn AnyStorage>>>,
+}
+
+impl World {
+    pub fn new() -> World {
+        World::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        if let Some(index) = self.free_slots.pop() {
+            self.alive[index as usize] = true;
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+
+        self.generations.push(0);
+        self.alive.push(true);
+        Entity {
+            index: self.generations.len() as u32 - 1,
+            generation: 0,
+        }
+    }
+
+    /// Removes the entity with all its components. Returns false for stale handles.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        let index = entity.index as usize;
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove(index);
+        }
+
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_slots.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len()
+            && self.alive[index]
+            && self.generations[index] == entity.generation
+    }
+
+    /// Number of live entities
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free_slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds or replaces a component. Ignored for stale handles.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) {
+        if !self.is_alive(entity) {
+            return;
+        }
+
+        let storage = self
+            .storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                RefCell::new(Box::new(Storage::<T> {
+                    components: Vec::new(),
+                }))
+            })
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+            .unwrap();
+
+        let index = entity.index as usize;
+        if storage.components.len() <= index {
+            storage.components.resize_with(index + 1, || None);
+        }
+        storage.components[index] = Some(component);
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        self.storage_mut::<T>()?
+            .components
+            .get_mut(entity.index as usize)?
+            .take()
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        let storage = borrow_storage::<T>(self)?;
+        Ref::filter_map(storage, |storage| {
+            storage.components.get(entity.index as usize)?.as_ref()
+        })
+        .ok()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        self.storage_mut::<T>()?
+            .components
+            .get_mut(entity.index as usize)?
+            .as_mut()
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    /// Calls `f` for every live entity that has all components in `Q`.
+    ///
+    /// `Q` is a component reference or a tuple of them, e.g. `(&Position, &mut Velocity)`.
+    pub fn query<Q: Query>(&self, mut f: impl FnMut(Entity, Q::Item<'_>)) {
+        // A missing storage means no entity can match
+        let Some(mut guard) = Q::borrow(self) else {
+            return;
+        };
+
+        for (index, &generation) in self.generations.iter().enumerate() {
+            if !self.alive[index] {
+                continue;
+            }
+
+            if let Some(item) = Q::fetch(&mut guard, index) {
+                let entity = Entity {
+                    index: index as u32,
+                    generation,
+                };
+                f(entity, item);
+            }
+        }
+    }
+
+    /// Entities that have all components in `Q`
+    pub fn entities_with<Q: Query>(&self) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.query::<Q>(|entity, _| entities.push(entity));
+        entities
+    }
+
+    fn storage_mut<T: 'static>(&mut self) -> Option<&mut Storage<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+    }
+}
+
+fn borrow_storage<T: 'static>(world: &World) -> Option<Ref<'_, Storage<T>>> {
+    let storage = world.storages.get(&TypeId::of::<T>())?.borrow();
+    Some(Ref::map(storage, |storage| {
+        storage.as_any().downcast_ref::<Storage<T>>().unwrap()
+    }))
+}
+
+fn borrow_storage_mut<T: 'static>(world: &World) -> Option<RefMut<'_, Storage<T>>> {
+    let storage = world.storages.get(&TypeId::of::<T>())?.borrow_mut();
+    Some(RefMut::map(storage, |storage| {
+        storage.as_any_mut().downcast_mut::<Storage<T>>().unwrap()
+    }))
+}
+
+/// Set of components fetched together by `World::query`
+pub trait Query {
+    type Guard<'w>;
+    type Item<'g>;
+
+    fn borrow(world: &World) -> Option<Self::Guard<'_>>;
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, index: usize) -> Option<Self::Item<'g>>;
+}
+
+impl<T: 'static> Query for &T {
+    type Guard<'w> = Ref<'w, Storage<T>>;
+    type Item<'g> = &'g T;
+
+    fn borrow(world: &World) -> Option<Self::Guard<'_>> {
+        borrow_storage::<T>(world)
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, index: usize) -> Option<Self::Item<'g>> {
+        guard.components.get(index)?.as_ref()
+    }
+}
+
+impl<T: 'static> Query for &mut T {
+    type Guard<'w> = RefMut<'w, Storage<T>>;
+    type Item<'g> = &'g mut T;
+
+    fn borrow(world: &World) -> Option<Self::Guard<'_>> {
+        borrow_storage_mut::<T>(world)
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, index: usize) -> Option<Self::Item<'g>> {
+        guard.components.get_mut(index)?.as_mut()
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Query),*> Query for ($($name,)*) {
+            type Guard<'w> = ($($name::Guard<'w>,)*);
+            type Item<'g> = ($($name::Item<'g>,)*);
+
+            fn borrow(world: &World) -> Option<Self::Guard<'_>> {
+                Some(($($name::borrow(world)?,)*))
+            }
+
+            #[allow(non_snake_case)]
+            fn fetch<'g>(guard: &'g mut Self::Guard<'_>, index: usize) -> Option<Self::Item<'g>> {
+                let ($($name,)*) = guard;
+                Some(($($name::fetch($name, index)?,)*))
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+
+    #[test]
+    fn despawned_slots_are_reused_with_a_new_generation() {
+        let mut world = World::new();
+        let first = world.spawn();
+        let second = world.spawn();
+        assert_eq!(world.len(), 2);
+
+        assert!(world.despawn(first));
+        assert!(!world.is_alive(first));
+        assert!(!world.despawn(first));
+        assert_eq!(world.len(), 1);
+
+        let reused = world.spawn();
+        assert_eq!(reused.index, first.index);
+        assert_ne!(reused, first);
+        assert!(world.is_alive(reused));
+        assert!(!world.is_alive(first));
+        assert!(world.is_alive(second));
+        assert_eq!(world.len(), 2);
+    }
+
+    #[test]
+    fn stale_handles_dont_reach_the_new_entity() {
+        let mut world = World::new();
+        let old = world.spawn();
+        world.insert(old, Position(1));
+        world.despawn(old);
+
+        let new = world.spawn();
+        assert!(world.get::<Position>(new).is_none());
+
+        world.insert(old, Position(2));
+        assert!(world.get::<Position>(new).is_none());
+        assert!(world.get::<Position>(old).is_none());
+        assert!(world.remove::<Position>(old).is_none());
+    }
+
+    #[test]
+    fn components_are_inserted_replaced_and_removed() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        assert!(!world.has::<Position>(entity));
+
+        world.insert(entity, Position(1));
+        assert_eq!(*world.get::<Position>(entity).unwrap(), Position(1));
+
+        world.insert(entity, Position(2));
+        world.get_mut::<Position>(entity).unwrap().0 += 1;
+        assert_eq!(*world.get::<Position>(entity).unwrap(), Position(3));
+
+        assert_eq!(world.remove::<Position>(entity), Some(Position(3)));
+        assert!(!world.has::<Position>(entity));
+        assert_eq!(world.remove::<Position>(entity), None);
+    }
+
+    #[test]
+    fn despawn_removes_all_components() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Position(1));
+        world.insert(entity, Velocity(1));
+        world.despawn(entity);
+
+        let reused = world.spawn();
+        assert!(!world.has::<Position>(reused));
+        assert!(!world.has::<Velocity>(reused));
+    }
+
+    #[test]
+    fn queries_match_entities_with_all_components() {
+        let mut world = World::new();
+        let moving = world.spawn();
+        world.insert(moving, Position(0));
+        world.insert(moving, Velocity(2));
+        let still = world.spawn();
+        world.insert(still, Position(5));
+        let named = world.spawn();
+        world.insert(named, Position(1));
+        world.insert(named, Velocity(-1));
+        world.insert(named, Name("named"));
+        let removed = world.spawn();
+        world.insert(removed, Position(0));
+        world.insert(removed, Velocity(0));
+        world.despawn(removed);
+
+        world.query::<(&mut Position, &Velocity)>(|_, (position, velocity)| {
+            position.0 += velocity.0;
+        });
+        assert_eq!(*world.get::<Position>(moving).unwrap(), Position(2));
+        assert_eq!(*world.get::<Position>(still).unwrap(), Position(5));
+        assert_eq!(*world.get::<Position>(named).unwrap(), Position(0));
+
+        assert_eq!(
+            world.entities_with::<(&Position, &Velocity)>(),
+            vec![moving, named]
+        );
+        assert_eq!(world.entities_with::<&Name>(), vec![named]);
+        assert_eq!(world.entities_with::<&Position>().len(), 3);
+    }
+
+    #[test]
+    fn queries_on_missing_components_match_nothing() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Position(0));
+        assert!(world.entities_with::<(&Position, &Name)>().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn querying_a_component_twice_panics() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Position(0));
+        world.query::<(&mut Position, &Position)>(|_, _| {});
+    }
+}
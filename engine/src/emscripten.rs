@@ -10,12 +10,22 @@
 // to prevent scopes from being exited which would cause objects to be destroyed prematurely. To be able to destroy the data object properly
 // as looping is terminated, the object is stored in thread_local storage.
 
-use std::cell::RefCell;
-use std::os::raw::c_int;
+use std::cell::{Cell, RefCell};
+use std::os::raw::{c_char, c_int, c_void};
 
 // Declare our FFI to the Emscripten functions we need. These will be linked in when building for Emscripten targets.
 #[allow(non_camel_case_types)]
 type em_callback_func = unsafe extern "C" fn();
+/// Event handler taking the event type, the event struct and the user data
+#[allow(non_camel_case_types)]
+type em_event_callback_func = unsafe extern "C" fn(c_int, *const c_void, *mut c_void) -> c_int;
+
+/// `EMSCRIPTEN_EVENT_TARGET_DOCUMENT`
+const EVENT_TARGET_DOCUMENT: usize = 1;
+/// `EMSCRIPTEN_EVENT_TARGET_WINDOW`
+const EVENT_TARGET_WINDOW: usize = 2;
+/// `EM_CALLBACK_THREAD_CONTEXT_CALLING_THREAD`
+const CALLING_THREAD: usize = 2;
 
 extern "C" {
     pub fn emscripten_set_main_loop(
@@ -26,15 +36,42 @@ extern "C" {
 
     pub fn emscripten_get_now() -> f64;
 
+    pub fn emscripten_request_pointerlock(
+        target: *const c_char,
+        defer_until_in_event_handler: c_int,
+    ) -> c_int;
+
+    pub fn emscripten_exit_pointerlock() -> c_int;
+
+    pub fn emscripten_get_pointerlock_status(status: *mut PointerlockChangeEvent) -> c_int;
+
+    pub fn emscripten_run_script(script: *const c_char);
+
+    pub fn emscripten_run_script_string(script: *const c_char) -> *const c_char;
+
+    pub fn emscripten_run_script_int(script: *const c_char) -> c_int;
+
+    pub fn emscripten_set_resize_callback_on_thread(
+        target: *const c_char,
+        user_data: *mut c_void,
+        use_capture: c_int,
+        callback: em_event_callback_func,
+        thread: usize,
+    ) -> c_int;
+
+    pub fn emscripten_set_fullscreenchange_callback_on_thread(
+        target: *const c_char,
+        user_data: *mut c_void,
+        use_capture: c_int,
+        callback: em_event_callback_func,
+        thread: usize,
+    ) -> c_int;
+
     pub fn emscripten_get_screen_size(width: *mut c_int, height: *mut c_int);
 
-    pub fn emscripten_cancel_main_loop();
+    pub fn emscripten_get_device_pixel_ratio() -> f64;
 
-    pub fn emscripten_get_canvas_element_size(
-        target: *const u8,
-        width: *mut c_int,
-        height: *mut c_int,
-    );
+    pub fn emscripten_cancel_main_loop();
 
     pub fn emscripten_set_canvas_element_size(
         target: *const u8,
@@ -43,6 +80,14 @@ extern "C" {
     ) -> c_int;
 }
 
+/// `EmscriptenPointerlockChangeEvent`
+#[repr(C)]
+pub struct PointerlockChangeEvent {
+    is_active: c_int,
+    node_name: [c_char; 128],
+    id: [c_char; 128],
+}
+
 thread_local! {
     // This is where the data object will be kept during the scheduled looping. The storage structure is justified as follows
 
@@ -52,6 +97,9 @@ thread_local! {
     // Box<dyn ...> - make it work generically for any closure passed in
 
     static MAIN_LOOP_CLOSURE: RefCell<Option<Box<dyn FnMut()>>> = RefCell::new(None);
+
+    // Set by the browser's resize and fullscreen events, handled by the next frame
+    static RESIZED: Cell<bool> = const { Cell::new(false) };
 }
 
 pub fn get_screen_size() -> (u32, u32) {
@@ -63,24 +111,85 @@ pub fn get_screen_size() -> (u32, u32) {
     (width as u32, height as u32)
 }
 
-pub fn get_canvas_element_size() -> (u32, u32) {
-    let mut width = 0;
-    let mut height = 0;
+/// Canvas pixels per CSS pixel, above 1 on HiDPI screens
+pub fn device_pixel_ratio() -> crate::Float {
+    unsafe { emscripten_get_device_pixel_ratio() as crate::Float }
+}
+
+pub fn set_canvas_element_size(width: u32, height: u32) {
     let target = b"canvas\0";
+    unsafe {
+        emscripten_set_canvas_element_size(target.as_ptr(), width as c_int, height as c_int);
+    }
+}
 
+/// Locks the pointer to the canvas. Browsers only allow that in response to user input,
+/// so the request waits for the next click or key press.
+pub fn request_pointer_lock() {
+    let target = b"#canvas\0";
     unsafe {
-        emscripten_get_canvas_element_size(target.as_ptr(), &mut width, &mut height);
+        emscripten_request_pointerlock(target.as_ptr() as *const c_char, 1);
     }
-    (width as u32, height as u32)
 }
 
-pub fn set_canvas_element_size(width: u32, height: u32) {
-    let target = b"canvas\0";
+pub fn exit_pointer_lock() {
     unsafe {
-        emscripten_set_canvas_element_size(target.as_ptr(), width as c_int, height as c_int);
+        emscripten_exit_pointerlock();
     }
 }
 
+/// The browser also releases the lock by itself, e.g. when Escape is pressed
+pub fn is_pointer_locked() -> bool {
+    let mut status = PointerlockChangeEvent {
+        is_active: 0,
+        node_name: [0; 128],
+        id: [0; 128],
+    };
+    unsafe {
+        emscripten_get_pointerlock_status(&mut status);
+    }
+    status.is_active != 0
+}
+
+/// Starts listening for browser window resizes and fullscreen changes, see `take_resize`
+pub fn watch_resize() {
+    unsafe extern "C" fn on_resize(_: c_int, _: *const c_void, _: *mut c_void) -> c_int {
+        RESIZED.with(|resized| resized.set(true));
+        0
+    }
+
+    unsafe {
+        emscripten_set_resize_callback_on_thread(
+            EVENT_TARGET_WINDOW as *const c_char,
+            std::ptr::null_mut(),
+            0,
+            on_resize,
+            CALLING_THREAD,
+        );
+        emscripten_set_fullscreenchange_callback_on_thread(
+            EVENT_TARGET_DOCUMENT as *const c_char,
+            std::ptr::null_mut(),
+            0,
+            on_resize,
+            CALLING_THREAD,
+        );
+    }
+}
+
+/// True once after the browser window was resized or entered or left fullscreen
+pub fn take_resize() -> bool {
+    RESIZED.with(|resized| resized.replace(false))
+}
+
+/// Size of the browser window's viewport in CSS pixels
+pub fn get_window_inner_size() -> (u32, u32) {
+    let width_script = b"window.innerWidth\0";
+    let height_script = b"window.innerHeight\0";
+    let width = unsafe { emscripten_run_script_int(width_script.as_ptr() as *const c_char) };
+    let height = unsafe { emscripten_run_script_int(height_script.as_ptr() as *const c_char) };
+    (width.max(0) as u32, height.max(0) as u32)
+}
+
 // Schedules the given callback to be run over and over in a loop until it returns MainLoopEvent::Terminate.
 // Retains ownership of the passed callback
 pub fn set_main_loop_callback<F: FnMut() + 'static>(callback: F) {
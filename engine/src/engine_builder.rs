@@ -1,6 +1,9 @@
-use crate::{Dimensions, Engine, Game};
-use sdl2::pixels::Color;
+use crate::config::{EngineConfig, ScalingMode};
+use crate::time::{SystemTimeSource, TimeSource};
+use crate::window::WindowState;
+use crate::{Color, Dimensions, Engine, Float, Game, Plugin, QuitOnEscape};
 use std::cell::RefCell;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 pub struct EngineBuilder {
@@ -8,6 +11,12 @@ pub struct EngineBuilder {
     game_title: String,
     dimensions: Dimensions,
     background_color: Color,
+    time_source: Box<dyn TimeSource>,
+    asset_root: PathBuf,
+    config: EngineConfig,
+    scaling: ScalingMode,
+    plugins: Vec<Box<dyn Plugin>>,
+    default_plugins: bool,
 }
 
 pub fn create<T>(game: T, game_title: String) -> EngineBuilder
@@ -19,6 +28,12 @@ where
         game_title,
         dimensions: Dimensions::default(),
         background_color: Color::BLACK,
+        time_source: Box::new(SystemTimeSource::new()),
+        asset_root: PathBuf::from("assets"),
+        config: EngineConfig::default(),
+        scaling: ScalingMode::Fixed,
+        plugins: Vec::new(),
+        default_plugins: true,
     }
 }
 
@@ -28,6 +43,36 @@ impl EngineBuilder {
         self
     }
 
+    /// Replaces the platform clock, e.g. with a `ManualTimeSource` in tests
+    pub fn with_time_source<T>(mut self, time_source: T) -> EngineBuilder
+    where
+        T: TimeSource + 'static,
+    {
+        self.time_source = Box::new(time_source);
+        self
+    }
+
+    /// Directory that `Engine::load` resolves relative paths against, "assets" by default
+    pub fn with_asset_root(mut self, root: impl Into<PathBuf>) -> EngineBuilder {
+        self.asset_root = root.into();
+        self
+    }
+
+    /// Adds a plugin, hooks run in the order the plugins were added
+    pub fn with_plugin<P>(mut self, plugin: P) -> EngineBuilder
+    where
+        P: Plugin + 'static,
+    {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Leaves out the plugins registered by default, currently only `QuitOnEscape`
+    pub fn without_default_plugins(mut self) -> EngineBuilder {
+        self.default_plugins = false;
+        self
+    }
+
     pub fn with_dimensions(
         mut self,
         point_size: u32,
@@ -35,48 +80,138 @@ impl EngineBuilder {
         height_in_points: u32,
     ) -> EngineBuilder {
         self.dimensions = Dimensions::new(point_size, width_in_points, height_in_points);
+        self.scaling = ScalingMode::Fixed;
         self
     }
 
+    /// The point size fits the display, it's measured in `build`.
+    /// On the web, the point size also follows the browser window as it's resized.
     pub fn with_point_dimensions(
         mut self,
         width_in_points: u32,
         height_in_points: u32,
     ) -> EngineBuilder {
-        let (width, height) = EngineBuilder::get_screen_size();
-
-        let point_width = width / width_in_points;
-        let point_height = height / height_in_points;
-        let point_size = std::cmp::min(point_width, point_height);
+        self.dimensions = Dimensions::new(1, width_in_points, height_in_points);
+        self.scaling = ScalingMode::Fit;
+        self
+    }
 
-        self.dimensions = Dimensions::new(point_size, width_in_points, height_in_points);
+    /// The points cover the display, it's measured in `build`.
+    /// On the web, points are also added or removed as the browser window is resized.
+    pub fn with_stretched_dimensions(mut self, point_size: u32) -> EngineBuilder {
+        self.dimensions = Dimensions::new(point_size, 1, 1);
+        self.scaling = ScalingMode::Stretch;
+        self
+    }
 
+    /// Applies a loaded configuration. Title and dimensions set in the config replace the
+    /// ones given in code, whether this is called before or after the other `with_*` methods.
+    pub fn with_config(mut self, config: EngineConfig) -> EngineBuilder {
+        self.config = config;
         self
     }
 
-    pub fn with_stretched_dimensions(mut self, point_size: u32) -> EngineBuilder {
-        let (width, height) = EngineBuilder::get_screen_size();
+    /// Resolves the dimensions given in code and the config against the display
+    fn apply_config(&mut self) {
+        if self.config.headless {
+            // Has to be set before SDL is first initialized, also by `get_screen_size`
+            sdl2::hint::set("SDL_VIDEODRIVER", "dummy");
+        }
+
+        if let Some(title) = &self.config.title {
+            self.game_title = title.clone();
+        }
 
-        let (dim_width, dim_height) = (width / point_size, height / point_size);
-        self.dimensions = Dimensions::new(point_size, dim_width, dim_height);
+        let config = &self.config;
+        let needs_screen = self.scaling != ScalingMode::Fixed
+            || matches!(
+                config.scaling,
+                Some(ScalingMode::Fit | ScalingMode::Stretch)
+            );
+        let (screen_width, screen_height) = if needs_screen {
+            EngineBuilder::get_screen_size(config.display)
+        } else {
+            (0, 0)
+        };
 
-        self
+        let mut dimensions = self
+            .dimensions
+            .resized(self.scaling, screen_width, screen_height);
+        match config.scaling {
+            Some(ScalingMode::Fit) => {
+                let width = config.width.unwrap_or(dimensions.point_width());
+                let height = config.height.unwrap_or(dimensions.point_height());
+                dimensions = Dimensions::new(1, width, height).resized(
+                    ScalingMode::Fit,
+                    screen_width,
+                    screen_height,
+                );
+                self.scaling = ScalingMode::Fit;
+            }
+            Some(ScalingMode::Stretch) => {
+                let point_size = config.scale.unwrap_or(dimensions.point_size());
+                dimensions = Dimensions::new(point_size, 1, 1).resized(
+                    ScalingMode::Stretch,
+                    screen_width,
+                    screen_height,
+                );
+                self.scaling = ScalingMode::Stretch;
+            }
+            Some(ScalingMode::Fixed) | None => {
+                if config.scale.is_some() || config.width.is_some() || config.height.is_some() {
+                    dimensions = Dimensions::new(
+                        config.scale.unwrap_or(dimensions.point_size()),
+                        config.width.unwrap_or(dimensions.point_width()),
+                        config.height.unwrap_or(dimensions.point_height()),
+                    );
+                    self.scaling = ScalingMode::Fixed;
+                }
+            }
+        }
+        self.dimensions = dimensions;
     }
 
-    pub fn build(self) -> Engine {
-        Engine::new(
+    pub fn build(mut self) -> Engine {
+        self.apply_config();
+
+        // Stretched points cover the window, so its remembered size decides how many there are
+        if self.config.remember_window && self.config.scaling == Some(ScalingMode::Stretch) {
+            if let Some(state) = WindowState::load(&self.game_title) {
+                let point_size = self.dimensions.point_size().max(1);
+                self.dimensions = Dimensions::new(
+                    point_size,
+                    state.width / point_size,
+                    state.height / point_size,
+                );
+            }
+        }
+
+        if self.default_plugins {
+            // Default plugins run before the ones added with `with_plugin`
+            self.plugins.insert(0, Box::new(QuitOnEscape));
+        }
+
+        let mut engine = Engine::new(
             self.game,
             self.game_title,
             self.dimensions,
             self.background_color,
-        )
+            self.time_source,
+            self.asset_root,
+            self.config,
+        );
+        engine.plugins = self.plugins;
+        engine.scaling = self.scaling;
+        engine
     }
 
     pub fn start(self) {
         self.build().start();
     }
 
-    fn get_screen_size() -> (u32, u32) {
+    /// Size of `display` in drawable pixels, or of the canvas on the web, which is larger
+    /// than the reported screen size on HiDPI displays
+    fn get_screen_size(display: u32) -> (u32, u32) {
         let width: u32;
         let height: u32;
 
@@ -84,13 +219,37 @@ impl EngineBuilder {
         {
             let sdl = sdl2::init().unwrap();
             let video = sdl.video().unwrap();
-            let screen_size = video.display_bounds(0).unwrap();
-            (width, height) = (screen_size.width(), screen_size.height());
+            let display = if video.display_bounds(display as i32).is_ok() {
+                display as i32
+            } else {
+                0
+            };
+            let screen_size = video.display_bounds(display).unwrap();
+
+            // Only a window knows its pixel ratio, so a hidden one is opened on the display
+            let position = sdl2::sys::SDL_WINDOWPOS_CENTERED_MASK as i32 | display;
+            let pixel_ratio = video
+                .window("", 100, 100)
+                .position(position, position)
+                .allow_highdpi()
+                .hidden()
+                .build()
+                .map_or(1.0, |probe| {
+                    probe.drawable_size().0 as Float / probe.size().0.max(1) as Float
+                });
+
+            width = (screen_size.width() as Float * pixel_ratio) as u32;
+            height = (screen_size.height() as Float * pixel_ratio) as u32;
         }
 
         #[cfg(target_family = "wasm")]
         {
-            (width, height) = crate::emscripten::get_canvas_element_size();
+            let _ = display;
+            // Same source as `Engine::resize_to_browser`, so the first resize agrees with it
+            let (window_width, window_height) = crate::emscripten::get_window_inner_size();
+            let pixel_ratio = crate::emscripten::device_pixel_ratio();
+            width = (window_width as Float * pixel_ratio) as u32;
+            height = (window_height as Float * pixel_ratio) as u32;
         }
 
         (width, height)
@@ -0,0 +1,125 @@
+use crate::{Dimensions, Key, MouseButton};
+
+/// Input and window events delivered to `Game::handle_event`.
+/// Mouse positions are given in points, not pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Quit,
+    KeyDown {
+        key: Option<Key>,
+        repeat: bool,
+    },
+    KeyUp {
+        key: Option<Key>,
+        repeat: bool,
+    },
+    /// `dx` and `dy` are the movement in pixels, for finer control than whole points.
+    /// They keep coming in relative mouse mode, see `Engine::set_relative_mouse_mode`.
+    MouseMotion {
+        x: i32,
+        y: i32,
+        dx: i32,
+        dy: i32,
+    },
+    MouseButtonDown {
+        button: MouseButton,
+        x: i32,
+        y: i32,
+    },
+    MouseButtonUp {
+        button: MouseButton,
+        x: i32,
+        y: i32,
+    },
+    MouseWheel {
+        x: i32,
+        y: i32,
+    },
+    /// Committed text while text input is active, see `Engine::start_text_input`
+    TextInput {
+        text: String,
+    },
+    /// The screen changed size, e.g. when the browser window was resized. `width` and
+    /// `height` are the new `Dimensions` in points.
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    /// Uncommitted IME composition. `start` and `length` select part of `text`, in characters.
+    TextEditing {
+        text: String,
+        start: i32,
+        length: i32,
+    },
+}
+
+impl Event {
+    /// Converts an SDL event. Events the engine doesn't expose yield `None`.
+    pub(crate) fn from_sdl(event: sdl2::event::Event, dimensions: &Dimensions) -> Option<Event> {
+        use sdl2::event::Event as SdlEvent;
+
+        let to_points = |x: i32, y: i32| {
+            let (x, y) = dimensions.to_pixels(x, y);
+            let point_size = dimensions.point_size() as i32;
+            (x.div_euclid(point_size), y.div_euclid(point_size))
+        };
+
+        let event = match event {
+            SdlEvent::Quit { .. } => Event::Quit,
+            SdlEvent::KeyDown {
+                keycode, repeat, ..
+            } => Event::KeyDown {
+                key: keycode.and_then(Key::from_sdl),
+                repeat,
+            },
+            SdlEvent::KeyUp {
+                keycode, repeat, ..
+            } => Event::KeyUp {
+                key: keycode.and_then(Key::from_sdl),
+                repeat,
+            },
+            SdlEvent::MouseMotion {
+                x, y, xrel, yrel, ..
+            } => {
+                let (x, y) = to_points(x, y);
+                let (dx, dy) = dimensions.to_pixels(xrel, yrel);
+                Event::MouseMotion { x, y, dx, dy }
+            }
+            SdlEvent::MouseButtonDown {
+                mouse_btn, x, y, ..
+            } => {
+                let (x, y) = to_points(x, y);
+                Event::MouseButtonDown {
+                    button: MouseButton::from_sdl(mouse_btn)?,
+                    x,
+                    y,
+                }
+            }
+            SdlEvent::MouseButtonUp {
+                mouse_btn, x, y, ..
+            } => {
+                let (x, y) = to_points(x, y);
+                Event::MouseButtonUp {
+                    button: MouseButton::from_sdl(mouse_btn)?,
+                    x,
+                    y,
+                }
+            }
+            SdlEvent::MouseWheel { x, y, .. } => Event::MouseWheel { x, y },
+            SdlEvent::TextInput { text, .. } => Event::TextInput { text },
+            SdlEvent::TextEditing {
+                text,
+                start,
+                length,
+                ..
+            } => Event::TextEditing {
+                text,
+                start,
+                length,
+            },
+            _ => return None,
+        };
+
+        Some(event)
+    }
+}
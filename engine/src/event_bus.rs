@@ -0,0 +1,100 @@
+use crate::Engine;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Bounds how often handlers publishing new events can re-trigger delivery within one frame
+const MAX_DELIVERY_ROUNDS: usize = 16;
+
+/// Event published by the game, see `Engine::publish`
+pub struct UserEvent(Box<dyn Any>);
+
+impl UserEvent {
+    pub fn is<E: 'static>(&self) -> bool {
+        self.0.is::<E>()
+    }
+
+    pub fn get<E: 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+}
+
+/// Returned by `Engine::subscribe`, used to remove the handler again
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Subscription(u64);
+
+type Handler = Box<dyn FnMut(&UserEvent)>;
+
+#[derive(Default)]
+pub(crate) struct EventBus {
+    queue: RefCell<Vec<UserEvent>>,
+    handlers: HashMap<TypeId, Vec<(Subscription, Handler)>>,
+    next_subscription: u64,
+}
+
+impl EventBus {
+    /// Takes the events published so far. Events published while they are handled
+    /// end up in the next batch.
+    pub(crate) fn take_pending(&self) -> Vec<UserEvent> {
+        std::mem::take(&mut *self.queue.borrow_mut())
+    }
+
+    fn notify_subscribers(&mut self, event: &UserEvent) {
+        if let Some(handlers) = self.handlers.get_mut(&(*event.0).type_id()) {
+            for (_, handler) in handlers {
+                handler(event);
+            }
+        }
+    }
+}
+
+// Event bus API
+impl Engine {
+    /// Queues `event` for delivery after the current `Game::update` returns and before
+    /// `Game::render`. Subscribers are called first, in subscription order, then
+    /// `Game::handle_user_event`. Events published during `render` or `handle_event`
+    /// are delivered in the next frame.
+    pub fn publish<E: 'static>(&self, event: E) {
+        self.event_bus
+            .queue
+            .borrow_mut()
+            .push(UserEvent(Box::new(event)));
+    }
+
+    /// Calls `handler` for every published event of type `E`
+    pub fn subscribe<E: 'static>(&mut self, mut handler: impl FnMut(&E) + 'static) -> Subscription {
+        let bus = &mut self.event_bus;
+        let subscription = Subscription(bus.next_subscription);
+        bus.next_subscription += 1;
+
+        let handler: Handler = Box::new(move |event| handler(event.get().unwrap()));
+        bus.handlers
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push((subscription, handler));
+
+        subscription
+    }
+
+    pub fn unsubscribe(&mut self, subscription: Subscription) {
+        for handlers in self.event_bus.handlers.values_mut() {
+            handlers.retain(|(id, _)| *id != subscription);
+        }
+    }
+
+    pub(crate) fn deliver_user_events(&mut self) {
+        let game = self.game.clone();
+
+        for _ in 0..MAX_DELIVERY_ROUNDS {
+            let events = self.event_bus.take_pending();
+            if events.is_empty() {
+                return;
+            }
+
+            for event in events {
+                self.event_bus.notify_subscribers(&event);
+                game.borrow_mut().handle_user_event(&event, self);
+            }
+        }
+    }
+}
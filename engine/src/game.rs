@@ -1,5 +1,4 @@
-use crate::{Engine, Float};
-use sdl2::event::Event;
+use crate::{Engine, Event, Float, UserEvent};
 
 pub trait Game {
     fn handle_event(&mut self, event: Event) -> Option<Event> {
@@ -8,4 +7,7 @@ pub trait Game {
 
     fn update(&mut self, dt: Float, engine: &Engine);
     fn render(&mut self, engine: &mut Engine);
+
+    /// Receives events published with `Engine::publish`, between `update` and `render`
+    fn handle_user_event(&mut self, _event: &UserEvent, _engine: &mut Engine) {}
 }
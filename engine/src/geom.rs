@@ -0,0 +1,850 @@
+//! Shapes and collision tests in point space.
+//! All coordinates are `Float` points with the y axis pointing down, like the draw calls.
+
+use crate::{Dimensions, Float, Point, Vec2};
+
+/// Axis-aligned rectangle. `position` is the top-left corner.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub position: Vec2<Float>,
+    pub size: Vec2<Float>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Circle {
+    pub center: Vec2<Float>,
+    pub radius: Float,
+}
+
+/// Line segment between two points
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Line {
+    pub start: Vec2<Float>,
+    pub end: Vec2<Float>,
+}
+
+/// Half-line starting at `origin`. Hit distances are measured in multiples of `direction`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Ray {
+    pub origin: Vec2<Float>,
+    pub direction: Vec2<Float>,
+}
+
+/// Convex polygon. Points can be given in either winding order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polygon {
+    pub points: Vec<Vec2<Float>>,
+}
+
+/// Result of a ray cast or sweep
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    /// Distance along the ray (or fraction of the sweep) at which the hit occurs
+    pub t: Float,
+    pub point: Vec2<Float>,
+    /// Surface normal at the hit point, facing the ray. Zero if the ray starts inside the shape.
+    pub normal: Vec2<Float>,
+}
+
+/// Overlap between two shapes: moving the first one by `-normal * depth` separates them
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Penetration {
+    pub normal: Vec2<Float>,
+    pub depth: Float,
+}
+
+impl Rect {
+    pub fn new(x: Float, y: Float, width: Float, height: Float) -> Rect {
+        Rect {
+            position: Vec2::new(x, y),
+            size: Vec2::new(width, height),
+        }
+    }
+
+    pub fn from_corners(min: Vec2<Float>, max: Vec2<Float>) -> Rect {
+        Rect {
+            position: min,
+            size: max - min,
+        }
+    }
+
+    /// Rect covering the whole screen, in points
+    pub fn from_dimensions(dimensions: &Dimensions) -> Rect {
+        Rect::new(
+            0.0,
+            0.0,
+            dimensions.point_width() as Float,
+            dimensions.point_height() as Float,
+        )
+    }
+
+    pub fn min(&self) -> Vec2<Float> {
+        self.position
+    }
+
+    pub fn max(&self) -> Vec2<Float> {
+        self.position + self.size
+    }
+
+    pub fn center(&self) -> Vec2<Float> {
+        self.position + self.size / 2.0
+    }
+
+    pub fn width(&self) -> Float {
+        self.size.x
+    }
+
+    pub fn height(&self) -> Float {
+        self.size.y
+    }
+
+    pub fn translate(&self, offset: Vec2<Float>) -> Rect {
+        Rect {
+            position: self.position + offset,
+            size: self.size,
+        }
+    }
+
+    /// Grows the rect by `amount` on every side
+    pub fn expand(&self, amount: Vec2<Float>) -> Rect {
+        Rect {
+            position: self.position - amount,
+            size: self.size + amount * 2.0,
+        }
+    }
+
+    pub fn contains_point(&self, point: impl Into<Vec2<Float>>) -> bool {
+        let point = point.into();
+        let (min, max) = (self.min(), self.max());
+        point.x >= min.x && point.x <= max.x && point.y >= min.y && point.y <= max.y
+    }
+
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        self.contains_point(other.min()) && self.contains_point(other.max())
+    }
+
+    pub fn contains_circle(&self, circle: &Circle) -> bool {
+        self.contains_rect(&circle.bounds())
+    }
+
+    /// Touching edges do not count as an overlap
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        let (min, max) = (self.min(), self.max());
+        let (other_min, other_max) = (other.min(), other.max());
+        min.x < other_max.x && max.x > other_min.x && min.y < other_max.y && max.y > other_min.y
+    }
+
+    pub fn overlaps_circle(&self, circle: &Circle) -> bool {
+        circle.overlaps_rect(self)
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.overlaps(other) {
+            return None;
+        }
+
+        let (min, max) = (self.min(), self.max());
+        let (other_min, other_max) = (other.min(), other.max());
+        Some(Rect::from_corners(
+            Vec2::new(min.x.max(other_min.x), min.y.max(other_min.y)),
+            Vec2::new(max.x.min(other_max.x), max.y.min(other_max.y)),
+        ))
+    }
+
+    /// Minimum translation separating `self` from `other`, if they overlap
+    pub fn penetration(&self, other: &Rect) -> Option<Penetration> {
+        let overlap = self.intersection(other)?;
+        let delta = other.center() - self.center();
+
+        if overlap.width() < overlap.height() {
+            let sign = if delta.x < 0.0 { -1.0 } else { 1.0 };
+            Some(Penetration {
+                normal: Vec2::new(sign, 0.0),
+                depth: overlap.width(),
+            })
+        } else {
+            let sign = if delta.y < 0.0 { -1.0 } else { 1.0 };
+            Some(Penetration {
+                normal: Vec2::new(0.0, sign),
+                depth: overlap.height(),
+            })
+        }
+    }
+
+    /// Closest point inside the rect to `point`
+    pub fn clamp_point(&self, point: Vec2<Float>) -> Vec2<Float> {
+        let (min, max) = (self.min(), self.max());
+        Vec2::new(point.x.clamp(min.x, max.x), point.y.clamp(min.y, max.y))
+    }
+
+    pub fn to_polygon(&self) -> Polygon {
+        let (min, max) = (self.min(), self.max());
+        Polygon::new(vec![
+            min,
+            Vec2::new(max.x, min.y),
+            max,
+            Vec2::new(min.x, max.y),
+        ])
+    }
+
+    /// Moves `self` by `velocity` and returns the first moment it touches `target`.
+    /// `Hit::t` is the fraction of `velocity` travelled before the impact, in `[0, 1]`,
+    /// and `Hit::point` is the position of `self` at that moment.
+    pub fn sweep(&self, velocity: Vec2<Float>, target: &Rect) -> Option<Hit> {
+        // Shrink `self` to a point and grow `target` by its size instead
+        let expanded = Rect {
+            position: target.position - self.size,
+            size: target.size + self.size,
+        };
+        let ray = Ray::new(self.position, velocity);
+        let hit = ray.cast_rect(&expanded)?;
+
+        if hit.t > 1.0 {
+            return None;
+        }
+
+        Some(hit)
+    }
+
+    /// Top-left point and size in points, for use with `Engine::draw_rect`
+    pub fn to_points(&self) -> (Point, u32, u32) {
+        (
+            self.position.to_point(),
+            self.size.x.round().max(0.0) as u32,
+            self.size.y.round().max(0.0) as u32,
+        )
+    }
+}
+
+impl Circle {
+    pub fn new(center: impl Into<Vec2<Float>>, radius: Float) -> Circle {
+        Circle {
+            center: center.into(),
+            radius,
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let radius = Vec2::new(self.radius, self.radius);
+        Rect::from_corners(self.center - radius, self.center + radius)
+    }
+
+    pub fn contains_point(&self, point: impl Into<Vec2<Float>>) -> bool {
+        (point.into() - self.center).length_squared() <= self.radius * self.radius
+    }
+
+    pub fn contains_circle(&self, other: &Circle) -> bool {
+        other.radius <= self.radius
+            && (other.center - self.center).length() + other.radius <= self.radius
+    }
+
+    pub fn contains_rect(&self, rect: &Rect) -> bool {
+        rect.to_polygon()
+            .points
+            .into_iter()
+            .all(|corner| self.contains_point(corner))
+    }
+
+    /// Touching circles do not count as an overlap
+    pub fn overlaps(&self, other: &Circle) -> bool {
+        let radii = self.radius + other.radius;
+        (other.center - self.center).length_squared() < radii * radii
+    }
+
+    pub fn overlaps_rect(&self, rect: &Rect) -> bool {
+        let closest = rect.clamp_point(self.center);
+        (closest - self.center).length_squared() < self.radius * self.radius
+    }
+
+    pub fn penetration(&self, other: &Circle) -> Option<Penetration> {
+        if !self.overlaps(other) {
+            return None;
+        }
+
+        let delta = other.center - self.center;
+        let distance = delta.length();
+        let normal = if distance == 0.0 {
+            Vec2::new(1.0, 0.0)
+        } else {
+            delta / distance
+        };
+
+        Some(Penetration {
+            normal,
+            depth: self.radius + other.radius - distance,
+        })
+    }
+
+    pub fn penetration_rect(&self, rect: &Rect) -> Option<Penetration> {
+        if !self.overlaps_rect(rect) {
+            return None;
+        }
+
+        let closest = rect.clamp_point(self.center);
+        let delta = closest - self.center;
+        let distance = delta.length();
+
+        if distance == 0.0 {
+            // Center is inside the rect, push out through the nearest edge
+            let (min, max) = (rect.min(), rect.max());
+            let edges = [
+                (self.center.x - min.x, Vec2::new(1.0, 0.0)),
+                (max.x - self.center.x, Vec2::new(-1.0, 0.0)),
+                (self.center.y - min.y, Vec2::new(0.0, 1.0)),
+                (max.y - self.center.y, Vec2::new(0.0, -1.0)),
+            ];
+            let (edge_distance, normal) = edges
+                .into_iter()
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap();
+
+            return Some(Penetration {
+                normal,
+                depth: edge_distance + self.radius,
+            });
+        }
+
+        Some(Penetration {
+            normal: delta / distance,
+            depth: self.radius - distance,
+        })
+    }
+}
+
+impl Line {
+    pub fn new(start: impl Into<Vec2<Float>>, end: impl Into<Vec2<Float>>) -> Line {
+        Line {
+            start: start.into(),
+            end: end.into(),
+        }
+    }
+
+    pub fn direction(&self) -> Vec2<Float> {
+        self.end - self.start
+    }
+
+    pub fn length(&self) -> Float {
+        self.direction().length()
+    }
+
+    pub fn closest_point(&self, point: Vec2<Float>) -> Vec2<Float> {
+        let direction = self.direction();
+        let length_squared = direction.length_squared();
+        if length_squared == 0.0 {
+            return self.start;
+        }
+
+        let t = ((point - self.start).dot(direction) / length_squared).clamp(0.0, 1.0);
+        self.start + direction * t
+    }
+
+    /// Crossing point of two segments
+    pub fn intersection(&self, other: &Line) -> Option<Vec2<Float>> {
+        let hit = Ray::new(self.start, self.direction()).cast_line(other)?;
+        if hit.t > 1.0 {
+            return None;
+        }
+        Some(hit.point)
+    }
+}
+
+impl Ray {
+    pub fn new(origin: impl Into<Vec2<Float>>, direction: Vec2<Float>) -> Ray {
+        Ray {
+            origin: origin.into(),
+            direction,
+        }
+    }
+
+    pub fn point_at(&self, t: Float) -> Vec2<Float> {
+        self.origin + self.direction * t
+    }
+
+    pub fn cast_rect(&self, rect: &Rect) -> Option<Hit> {
+        let (min, max) = (rect.min(), rect.max());
+        let axes = [
+            (
+                self.origin.x,
+                self.direction.x,
+                min.x,
+                max.x,
+                Vec2::new(1.0, 0.0),
+            ),
+            (
+                self.origin.y,
+                self.direction.y,
+                min.y,
+                max.y,
+                Vec2::new(0.0, 1.0),
+            ),
+        ];
+
+        let mut t_near = Float::NEG_INFINITY;
+        let mut t_far = Float::INFINITY;
+        let mut normal = Vec2::ZERO;
+
+        // Slab test: intersect the ray with the entry and exit of each axis
+        for (origin, direction, low, high, axis) in axes {
+            if direction == 0.0 {
+                if origin < low || origin > high {
+                    return None;
+                }
+                continue;
+            }
+
+            let t_low = (low - origin) / direction;
+            let t_high = (high - origin) / direction;
+            let (t_enter, t_exit, entry_normal) = if t_low < t_high {
+                (t_low, t_high, -axis)
+            } else {
+                (t_high, t_low, axis)
+            };
+
+            if t_enter > t_near {
+                t_near = t_enter;
+                normal = entry_normal;
+            }
+            t_far = t_far.min(t_exit);
+        }
+
+        if t_near > t_far || t_far < 0.0 {
+            return None;
+        }
+
+        Some(self.hit_or_inside(t_near, normal))
+    }
+
+    pub fn cast_circle(&self, circle: &Circle) -> Option<Hit> {
+        let offset = self.origin - circle.center;
+        let a = self.direction.length_squared();
+        if a == 0.0 {
+            return None;
+        }
+
+        let b = 2.0 * self.direction.dot(offset);
+        let c = offset.length_squared() - circle.radius * circle.radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let t_far = (-b + root) / (2.0 * a);
+        if t_far < 0.0 {
+            return None;
+        }
+
+        let t = (-b - root) / (2.0 * a);
+        let normal = (self.point_at(t) - circle.center).normalize();
+        Some(self.hit_or_inside(t, normal))
+    }
+
+    pub fn cast_line(&self, line: &Line) -> Option<Hit> {
+        let edge = line.direction();
+        let denominator = self.direction.cross(edge);
+        if denominator == 0.0 {
+            return None;
+        }
+
+        let offset = line.start - self.origin;
+        let t = offset.cross(edge) / denominator;
+        let u = offset.cross(self.direction) / denominator;
+        if t < 0.0 || !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let mut normal = edge.perpendicular().normalize();
+        if normal.dot(self.direction) > 0.0 {
+            normal = -normal;
+        }
+
+        Some(Hit {
+            t,
+            point: self.point_at(t),
+            normal,
+        })
+    }
+
+    pub fn cast_polygon(&self, polygon: &Polygon) -> Option<Hit> {
+        if polygon.contains_point(self.origin) {
+            return Some(self.hit_or_inside(0.0, Vec2::ZERO));
+        }
+
+        polygon
+            .edges()
+            .filter_map(|edge| self.cast_line(&edge))
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+    }
+
+    fn hit_or_inside(&self, t: Float, normal: Vec2<Float>) -> Hit {
+        if t < 0.0 {
+            return Hit {
+                t: 0.0,
+                point: self.origin,
+                normal: Vec2::ZERO,
+            };
+        }
+
+        Hit {
+            t,
+            point: self.point_at(t),
+            normal,
+        }
+    }
+}
+
+impl Polygon {
+    pub fn new(points: Vec<Vec2<Float>>) -> Polygon {
+        Polygon { points }
+    }
+
+    /// Regular polygon approximating a circle, e.g. for SAT tests against polygons
+    pub fn regular(center: Vec2<Float>, radius: Float, sides: usize) -> Polygon {
+        let step = std::f64::consts::TAU as Float / sides as Float;
+        Polygon::new(
+            (0..sides)
+                .map(|i| center + Vec2::new(radius, 0.0).rotate(step * i as Float))
+                .collect(),
+        )
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = Line> + '_ {
+        let count = self.points.len();
+        (0..count).map(move |i| Line::new(self.points[i], self.points[(i + 1) % count]))
+    }
+
+    pub fn center(&self) -> Vec2<Float> {
+        if self.points.is_empty() {
+            return Vec2::ZERO;
+        }
+
+        let sum = self
+            .points
+            .iter()
+            .fold(Vec2::ZERO, |sum, &point| sum + point);
+        sum / self.points.len() as Float
+    }
+
+    pub fn translate(&self, offset: Vec2<Float>) -> Polygon {
+        Polygon::new(self.points.iter().map(|&point| point + offset).collect())
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let mut min = Vec2::new(Float::INFINITY, Float::INFINITY);
+        let mut max = Vec2::new(Float::NEG_INFINITY, Float::NEG_INFINITY);
+        for point in &self.points {
+            min = Vec2::new(min.x.min(point.x), min.y.min(point.y));
+            max = Vec2::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        Rect::from_corners(min, max)
+    }
+
+    pub fn contains_point(&self, point: impl Into<Vec2<Float>>) -> bool {
+        let point = point.into();
+
+        // Even-odd rule: count edge crossings of a horizontal ray going right
+        let mut inside = false;
+        for edge in self.edges() {
+            let (a, b) = (edge.start, edge.end);
+            if (a.y > point.y) != (b.y > point.y) {
+                let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if point.x < x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    pub fn overlaps(&self, other: &Polygon) -> bool {
+        self.penetration(other).is_some()
+    }
+
+    /// Separating axis test. Both polygons must be convex.
+    pub fn penetration(&self, other: &Polygon) -> Option<Penetration> {
+        let mut result: Option<Penetration> = None;
+
+        for edge in self.edges().chain(other.edges()) {
+            let axis = edge.direction().perpendicular().normalize();
+            if axis == Vec2::ZERO {
+                continue;
+            }
+
+            let (min, max) = self.project(axis);
+            let (other_min, other_max) = other.project(axis);
+            let overlap = max.min(other_max) - min.max(other_min);
+            if overlap <= 0.0 {
+                return None;
+            }
+
+            if result.is_none_or(|best| overlap < best.depth) {
+                result = Some(Penetration {
+                    normal: axis,
+                    depth: overlap,
+                });
+            }
+        }
+
+        // Point the normal from `self` towards `other`
+        result.map(|mut penetration| {
+            if (other.center() - self.center()).dot(penetration.normal) < 0.0 {
+                penetration.normal = -penetration.normal;
+            }
+            penetration
+        })
+    }
+
+    fn project(&self, axis: Vec2<Float>) -> (Float, Float) {
+        self.points
+            .iter()
+            .map(|point| point.dot(axis))
+            .fold((Float::INFINITY, Float::NEG_INFINITY), |(min, max), p| {
+                (min.min(p), max.max(p))
+            })
+    }
+}
+
+impl From<Rect> for Polygon {
+    fn from(rect: Rect) -> Self {
+        rect.to_polygon()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: Float, y: Float) -> Vec2<Float> {
+        Vec2::new(x, y)
+    }
+
+    fn square(x: Float, y: Float, size: Float) -> Polygon {
+        Rect::new(x, y, size, size).to_polygon()
+    }
+
+    #[test]
+    fn rect_overlaps_rect() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert!(rect.overlaps(&Rect::new(5.0, 5.0, 10.0, 10.0)));
+        assert!(!rect.overlaps(&Rect::new(20.0, 0.0, 5.0, 5.0)));
+        // Touching edges
+        assert!(!rect.overlaps(&Rect::new(10.0, 0.0, 5.0, 5.0)));
+        assert_eq!(
+            rect.intersection(&Rect::new(5.0, 5.0, 10.0, 10.0)),
+            Some(Rect::new(5.0, 5.0, 5.0, 5.0))
+        );
+    }
+
+    #[test]
+    fn rect_contains() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert!(rect.contains_point(v(10.0, 5.0)));
+        assert!(!rect.contains_point(v(10.5, 5.0)));
+        assert!(rect.contains_rect(&Rect::new(2.0, 2.0, 8.0, 8.0)));
+        assert!(!rect.contains_rect(&Rect::new(2.0, 2.0, 9.0, 8.0)));
+        assert!(rect.contains_circle(&Circle::new(v(5.0, 5.0), 5.0)));
+        assert!(!rect.contains_circle(&Circle::new(v(5.0, 5.0), 6.0)));
+    }
+
+    #[test]
+    fn rect_penetration_uses_smallest_axis() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let penetration = rect.penetration(&Rect::new(8.0, 1.0, 10.0, 8.0)).unwrap();
+        assert_eq!(penetration.normal, v(1.0, 0.0));
+        assert_eq!(penetration.depth, 2.0);
+
+        let penetration = rect.penetration(&Rect::new(1.0, -7.0, 8.0, 10.0)).unwrap();
+        assert_eq!(penetration.normal, v(0.0, -1.0));
+        assert_eq!(penetration.depth, 3.0);
+
+        assert!(rect.penetration(&Rect::new(10.0, 0.0, 5.0, 5.0)).is_none());
+    }
+
+    #[test]
+    fn circle_overlaps_circle() {
+        let circle = Circle::new(v(0.0, 0.0), 5.0);
+        assert!(circle.overlaps(&Circle::new(v(8.0, 0.0), 5.0)));
+        // Touching
+        assert!(!circle.overlaps(&Circle::new(v(10.0, 0.0), 5.0)));
+
+        let penetration = circle.penetration(&Circle::new(v(8.0, 0.0), 5.0)).unwrap();
+        assert_eq!(penetration.normal, v(1.0, 0.0));
+        assert_eq!(penetration.depth, 2.0);
+    }
+
+    #[test]
+    fn circle_contains() {
+        let circle = Circle::new(v(0.0, 0.0), 5.0);
+        assert!(circle.contains_point(v(3.0, 4.0)));
+        assert!(!circle.contains_point(v(4.0, 4.0)));
+        assert!(circle.contains_circle(&Circle::new(v(2.0, 0.0), 3.0)));
+        assert!(!circle.contains_circle(&Circle::new(v(3.0, 0.0), 3.0)));
+        assert!(circle.contains_rect(&Rect::new(-3.0, -3.0, 6.0, 6.0)));
+        assert!(!circle.contains_rect(&Rect::new(-4.0, -4.0, 8.0, 8.0)));
+    }
+
+    #[test]
+    fn circle_overlaps_rect() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert!(Circle::new(v(-2.0, 5.0), 3.0).overlaps_rect(&rect));
+        assert!(rect.overlaps_circle(&Circle::new(v(-2.0, 5.0), 3.0)));
+        // Touching the edge
+        assert!(!Circle::new(v(-3.0, 5.0), 3.0).overlaps_rect(&rect));
+        // Near a corner but outside it
+        assert!(!Circle::new(v(-2.0, -2.0), 2.5).overlaps_rect(&rect));
+    }
+
+    #[test]
+    fn circle_rect_penetration_from_outside() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let penetration = Circle::new(v(-2.0, 5.0), 3.0)
+            .penetration_rect(&rect)
+            .unwrap();
+        assert_eq!(penetration.normal, v(1.0, 0.0));
+        assert_eq!(penetration.depth, 1.0);
+        assert!(Circle::new(v(-3.0, 5.0), 3.0)
+            .penetration_rect(&rect)
+            .is_none());
+    }
+
+    #[test]
+    fn circle_rect_penetration_with_center_inside() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 20.0);
+
+        // Nearest edge is the left one, even though the rect center is to the right
+        let penetration = Circle::new(v(2.0, 10.0), 1.0)
+            .penetration_rect(&rect)
+            .unwrap();
+        assert_eq!(penetration.normal, v(1.0, 0.0));
+        assert_eq!(penetration.depth, 3.0);
+
+        // Deeper than the circle's diameter
+        let penetration = Circle::new(v(50.0, 15.0), 1.0)
+            .penetration_rect(&rect)
+            .unwrap();
+        assert_eq!(penetration.normal, v(0.0, -1.0));
+        assert_eq!(penetration.depth, 6.0);
+
+        let penetration = Circle::new(v(97.0, 8.0), 2.0)
+            .penetration_rect(&rect)
+            .unwrap();
+        assert_eq!(penetration.normal, v(-1.0, 0.0));
+        assert_eq!(penetration.depth, 5.0);
+
+        // Center on the edge
+        let penetration = Circle::new(v(50.0, 0.0), 2.0)
+            .penetration_rect(&rect)
+            .unwrap();
+        assert_eq!(penetration.normal, v(0.0, 1.0));
+        assert_eq!(penetration.depth, 2.0);
+    }
+
+    #[test]
+    fn polygon_contains_point() {
+        let polygon = square(0.0, 0.0, 10.0);
+        assert!(polygon.contains_point(v(5.0, 5.0)));
+        assert!(!polygon.contains_point(v(15.0, 5.0)));
+
+        let triangle = Polygon::new(vec![v(0.0, 0.0), v(10.0, 0.0), v(0.0, 10.0)]);
+        assert!(triangle.contains_point(v(2.0, 2.0)));
+        assert!(!triangle.contains_point(v(8.0, 8.0)));
+    }
+
+    #[test]
+    fn polygon_overlaps_polygon() {
+        let polygon = square(0.0, 0.0, 10.0);
+        assert!(polygon.overlaps(&square(8.0, 2.0, 10.0)));
+        assert!(!polygon.overlaps(&square(20.0, 0.0, 10.0)));
+        // Touching edges
+        assert!(!polygon.overlaps(&square(10.0, 0.0, 10.0)));
+
+        // Separated only along a diagonal axis
+        let triangle = Polygon::new(vec![v(11.0, 6.0), v(16.0, 11.0), v(6.0, 11.0)]);
+        let diamond = Polygon::new(vec![v(0.0, 5.0), v(5.0, 0.0), v(10.0, 5.0), v(5.0, 10.0)]);
+        assert!(diamond.bounds().overlaps(&triangle.bounds()));
+        assert!(!diamond.overlaps(&triangle));
+
+        let penetration = polygon.penetration(&square(8.0, 2.0, 10.0)).unwrap();
+        assert_eq!(penetration.normal, v(1.0, 0.0));
+        assert!((penetration.depth - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rect_converts_to_polygon() {
+        let rect = Rect::new(1.0, 2.0, 3.0, 4.0);
+        let polygon = Polygon::from(rect);
+        assert_eq!(polygon.bounds(), rect);
+        assert_eq!(polygon.center(), rect.center());
+    }
+
+    #[test]
+    fn ray_casts() {
+        let ray = Ray::new(v(0.0, 5.0), v(1.0, 0.0));
+
+        let hit = ray.cast_rect(&Rect::new(10.0, 0.0, 10.0, 10.0)).unwrap();
+        assert_eq!(hit.t, 10.0);
+        assert_eq!(hit.normal, v(-1.0, 0.0));
+        assert!(ray.cast_rect(&Rect::new(10.0, 6.0, 10.0, 10.0)).is_none());
+        assert!(ray.cast_rect(&Rect::new(-20.0, 0.0, 10.0, 10.0)).is_none());
+
+        let hit = ray.cast_circle(&Circle::new(v(10.0, 5.0), 2.0)).unwrap();
+        assert_eq!(hit.t, 8.0);
+        assert_eq!(hit.normal, v(-1.0, 0.0));
+        assert!(ray.cast_circle(&Circle::new(v(10.0, 0.0), 2.0)).is_none());
+
+        let hit = ray
+            .cast_line(&Line::new(v(4.0, 0.0), v(4.0, 10.0)))
+            .unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.point, v(4.0, 5.0));
+        assert!(ray
+            .cast_line(&Line::new(v(4.0, 0.0), v(4.0, 4.0)))
+            .is_none());
+
+        let hit = ray.cast_polygon(&square(6.0, 0.0, 10.0)).unwrap();
+        assert_eq!(hit.t, 6.0);
+        assert_eq!(hit.normal, v(-1.0, 0.0));
+    }
+
+    #[test]
+    fn ray_starting_inside_hits_at_origin() {
+        let ray = Ray::new(v(5.0, 5.0), v(1.0, 0.0));
+        for hit in [
+            ray.cast_rect(&Rect::new(0.0, 0.0, 10.0, 10.0)),
+            ray.cast_circle(&Circle::new(v(5.0, 5.0), 2.0)),
+            ray.cast_polygon(&square(0.0, 0.0, 10.0)),
+        ] {
+            let hit = hit.unwrap();
+            assert_eq!(hit.t, 0.0);
+            assert_eq!(hit.normal, Vec2::ZERO);
+        }
+    }
+
+    #[test]
+    fn lines_intersect() {
+        let line = Line::new(v(0.0, 0.0), v(10.0, 10.0));
+        assert_eq!(
+            line.intersection(&Line::new(v(0.0, 10.0), v(10.0, 0.0))),
+            Some(v(5.0, 5.0))
+        );
+        assert!(line
+            .intersection(&Line::new(v(0.0, 10.0), v(4.0, 6.0)))
+            .is_none());
+        assert_eq!(line.closest_point(v(10.0, 0.0)), v(5.0, 5.0));
+        assert_eq!(line.closest_point(v(20.0, 20.0)), v(10.0, 10.0));
+    }
+
+    #[test]
+    fn sweep_finds_time_of_impact() {
+        let rect = Rect::new(0.0, 0.0, 2.0, 2.0);
+        let wall = Rect::new(10.0, -5.0, 2.0, 20.0);
+
+        let hit = rect.sweep(v(16.0, 0.0), &wall).unwrap();
+        assert_eq!(hit.t, 0.5);
+        assert_eq!(hit.point, v(8.0, 0.0));
+        assert_eq!(hit.normal, v(-1.0, 0.0));
+
+        assert!(rect.sweep(v(4.0, 0.0), &wall).is_none());
+        assert!(rect.sweep(v(0.0, 16.0), &wall).is_none());
+    }
+}
@@ -0,0 +1,542 @@
+//! Leaderboards kept in a `save::Storage`, with a name-entry prompt for new records.
+//!
+//! Stored tables carry a checksum. Files that were corrupted or edited by hand fail to
+//! load with `HighScoreError::Invalid`, and the game can start over with an empty table.
+
+use crate::save::Storage;
+use crate::{font, Color, Engine, Event, Float, Key, Point, TextBuffer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILE_VERSION: u32 = 1;
+pub const MAX_NAME_CHARS: usize = 16;
+
+/// Whether a higher or a lower score ranks first, e.g. points or solve times
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScoreOrder {
+    HighestFirst,
+    LowestFirst,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: Float,
+    /// Seconds since the Unix epoch
+    pub date: u64,
+    /// Game-specific details, e.g. the level or the seed
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ScoreEntry {
+    /// Entry dated now
+    pub fn new(name: &str, score: Float) -> ScoreEntry {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        ScoreEntry {
+            name: name.chars().take(MAX_NAME_CHARS).collect(),
+            score,
+            date,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: impl ToString) -> ScoreEntry {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Date as YYYY-MM-DD in UTC
+    pub fn date_string(&self) -> String {
+        let (year, month, day) = civil_date(self.date / 86_400);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+#[derive(Debug)]
+pub enum HighScoreError {
+    Io(io::Error),
+    /// The stored table is corrupt or was tampered with
+    Invalid(String),
+}
+
+impl fmt::Display for HighScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HighScoreError::Io(error) => write!(f, "Could not access high scores: {}", error),
+            HighScoreError::Invalid(reason) => write!(f, "Invalid high scores: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for HighScoreError {}
+
+#[derive(Serialize, Deserialize)]
+struct HighScoreFile {
+    version: u32,
+    entries: Vec<ScoreEntry>,
+    checksum: String,
+}
+
+/// Sorted table of the best scores, limited to `max_entries`
+#[derive(Clone, Debug)]
+pub struct HighScores {
+    entries: Vec<ScoreEntry>,
+    max_entries: usize,
+    order: ScoreOrder,
+}
+
+impl HighScores {
+    pub fn new(max_entries: usize, order: ScoreOrder) -> HighScores {
+        HighScores {
+            entries: Vec::new(),
+            max_entries,
+            order,
+        }
+    }
+
+    /// Best entry first
+    pub fn entries(&self) -> &[ScoreEntry] {
+        &self.entries
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// True if `score` would make it into the table
+    pub fn qualifies(&self, score: Float) -> bool {
+        score.is_finite() && self.rank_of(score) < self.max_entries
+    }
+
+    /// Adds `entry` and returns its rank starting at 0, or `None` if it didn't qualify.
+    /// Equal scores rank below the ones already in the table.
+    pub fn insert(&mut self, entry: ScoreEntry) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+
+        let rank = self.rank_of(entry.score);
+        self.entries.insert(rank, entry);
+        self.entries.truncate(self.max_entries);
+        Some(rank)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Reads the table from `slot`. An empty slot gives an empty table, entries beyond
+    /// `max_entries` are dropped.
+    pub fn load(
+        storage: &dyn Storage,
+        slot: &str,
+        max_entries: usize,
+        order: ScoreOrder,
+    ) -> Result<HighScores, HighScoreError> {
+        let mut high_scores = HighScores::new(max_entries, order);
+
+        let Some(data) = storage.read(slot).map_err(HighScoreError::Io)? else {
+            return Ok(high_scores);
+        };
+        let file: HighScoreFile = serde_json::from_str(&data)
+            .map_err(|error| HighScoreError::Invalid(error.to_string()))?;
+
+        if file.version != FILE_VERSION {
+            return Err(HighScoreError::Invalid(format!(
+                "Unknown version {}",
+                file.version
+            )));
+        }
+        if file.checksum != checksum(&file.entries) {
+            return Err(HighScoreError::Invalid("Checksum mismatch".to_string()));
+        }
+        for entry in &file.entries {
+            if !entry.score.is_finite() || entry.name.chars().count() > MAX_NAME_CHARS {
+                return Err(HighScoreError::Invalid(format!(
+                    "Invalid entry {:?}",
+                    entry.name
+                )));
+            }
+        }
+        let sorted = file
+            .entries
+            .windows(2)
+            .all(|pair| !order.is_better(pair[1].score, pair[0].score));
+        if !sorted {
+            return Err(HighScoreError::Invalid(
+                "Entries are out of order".to_string(),
+            ));
+        }
+
+        high_scores.entries = file.entries;
+        high_scores.entries.truncate(max_entries);
+        Ok(high_scores)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage, slot: &str) -> Result<(), HighScoreError> {
+        let file = HighScoreFile {
+            version: FILE_VERSION,
+            checksum: checksum(&self.entries),
+            entries: self.entries.clone(),
+        };
+        storage
+            .write(slot, &serde_json::to_string_pretty(&file).unwrap())
+            .map_err(HighScoreError::Io)
+    }
+
+    /// Draws one line per entry with rank, name, score and date, highlighting the entry at
+    /// `highlight`, e.g. the rank returned by `insert`
+    pub fn draw(
+        &self,
+        engine: &mut Engine,
+        position: impl Into<Point>,
+        color: Color,
+        highlight: Option<usize>,
+        format_score: impl Fn(Float) -> String,
+    ) {
+        let position = position.into();
+
+        for (rank, entry) in self.entries.iter().enumerate() {
+            let line = format!(
+                "{:>2}. {:<width$} {:>8} {}",
+                rank + 1,
+                entry.name,
+                format_score(entry.score),
+                entry.date_string(),
+                width = MAX_NAME_CHARS
+            );
+            let line_color = if highlight == Some(rank) {
+                Color::YELLOW
+            } else {
+                color
+            };
+            let y = position.y + (rank as u32 * font::LINE_HEIGHT) as i32;
+            engine.draw_text(&line, Point::new(position.x, y), line_color);
+        }
+    }
+
+    fn rank_of(&self, score: Float) -> usize {
+        self.entries
+            .iter()
+            .position(|entry| self.order.is_better(score, entry.score))
+            .unwrap_or(self.entries.len())
+    }
+}
+
+impl ScoreOrder {
+    fn is_better(self, score: Float, other: Float) -> bool {
+        match self {
+            ScoreOrder::HighestFirst => score > other,
+            ScoreOrder::LowestFirst => score < other,
+        }
+    }
+}
+
+/// Prompt for the name of a new high score, typed with the keyboard and confirmed with
+/// Return
+#[derive(Clone, Debug)]
+pub struct NameEntry {
+    buffer: TextBuffer,
+    submitted: bool,
+    text_input_started: bool,
+}
+
+impl Default for NameEntry {
+    fn default() -> Self {
+        NameEntry::new()
+    }
+}
+
+impl NameEntry {
+    pub fn new() -> NameEntry {
+        NameEntry {
+            buffer: TextBuffer::new().with_max_chars(MAX_NAME_CHARS),
+            submitted: false,
+            text_input_started: false,
+        }
+    }
+
+    /// Prefilled with e.g. the name entered last time
+    pub fn with_name(name: &str) -> NameEntry {
+        NameEntry {
+            buffer: TextBuffer::with_text(name).with_max_chars(MAX_NAME_CHARS),
+            ..NameEntry::new()
+        }
+    }
+
+    pub fn is_submitted(&self) -> bool {
+        self.submitted
+    }
+
+    /// Returns the trimmed name once a non-empty one is confirmed with Return.
+    /// Other events the prompt used return `None` as well and shouldn't reach the game.
+    pub fn handle_event(&mut self, event: &Event) -> Option<String> {
+        if self.submitted {
+            return None;
+        }
+
+        if let Event::KeyDown {
+            key: Some(Key::Return),
+            ..
+        } = event
+        {
+            let name = self.buffer.text().trim();
+            if !name.is_empty() && self.buffer.composition().is_empty() {
+                self.submitted = true;
+                return Some(name.to_string());
+            }
+        }
+
+        self.buffer.handle_event(event);
+        None
+    }
+
+    /// Draws `prompt` followed by the name and a cursor. Also starts text input while the
+    /// prompt is shown and stops it once the name is submitted.
+    pub fn draw(
+        &mut self,
+        engine: &mut Engine,
+        prompt: &str,
+        position: impl Into<Point>,
+        color: Color,
+    ) {
+        if !self.submitted && !self.text_input_started {
+            engine.start_text_input();
+            self.text_input_started = true;
+        } else if self.submitted && self.text_input_started {
+            engine.stop_text_input();
+            self.text_input_started = false;
+        }
+
+        let position = position.into();
+        let (prompt_width, _) = font::text_size(prompt);
+        let name_position = position + Point::new((prompt_width + font::ADVANCE) as i32, 0);
+        engine.draw_text(prompt, position, color);
+
+        let text = self.buffer.text();
+        let cursor = self.buffer.cursor();
+        let typed = format!(
+            "{}{}{}",
+            &text[..cursor],
+            self.buffer.composition(),
+            &text[cursor..]
+        );
+        engine.draw_text(&typed, name_position, color);
+
+        if !self.submitted {
+            let before_cursor =
+                text[..cursor].chars().count() + self.buffer.composition().chars().count();
+            let cursor_x = name_position.x + (before_cursor as u32 * font::ADVANCE) as i32;
+            engine.draw_rect(
+                Point::new(cursor_x, name_position.y + font::GLYPH_HEIGHT as i32),
+                font::GLYPH_WIDTH,
+                1,
+                color,
+            );
+            engine.set_text_input_rect(
+                name_position,
+                MAX_NAME_CHARS as u32 * font::ADVANCE,
+                font::LINE_HEIGHT,
+            );
+        }
+    }
+}
+
+/// FNV-1a hash of the serialized entries, catches corruption and casual edits
+fn checksum(entries: &[ScoreEntry]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in serde_json::to_string(entries).unwrap().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Year, month and day of the given day since the Unix epoch, after Howard Hinnant's
+/// `civil_from_days`
+fn civil_date(days: u64) -> (i64, u32, u32) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::MemoryStorage;
+
+    fn entry(name: &str, score: Float) -> ScoreEntry {
+        ScoreEntry {
+            date: 0,
+            ..ScoreEntry::new(name, score)
+        }
+    }
+
+    fn names(high_scores: &HighScores) -> Vec<&str> {
+        high_scores
+            .entries()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    /// Storage holding `entries` in the slot "scores" with a matching checksum
+    fn stored(version: u32, entries: Vec<ScoreEntry>) -> MemoryStorage {
+        let file = HighScoreFile {
+            version,
+            checksum: checksum(&entries),
+            entries,
+        };
+        let mut storage = MemoryStorage::new();
+        storage
+            .write("scores", &serde_json::to_string(&file).unwrap())
+            .unwrap();
+        storage
+    }
+
+    fn load(storage: &MemoryStorage) -> Result<HighScores, HighScoreError> {
+        HighScores::load(storage, "scores", 10, ScoreOrder::HighestFirst)
+    }
+
+    fn is_invalid(result: Result<HighScores, HighScoreError>) -> bool {
+        matches!(result, Err(HighScoreError::Invalid(_)))
+    }
+
+    #[test]
+    fn saved_tables_load_back() {
+        let mut high_scores = HighScores::new(10, ScoreOrder::HighestFirst);
+        high_scores.insert(entry("Ada", 30.0).with_metadata("level", 3));
+        high_scores.insert(entry("Bob", 20.0));
+
+        let mut storage = MemoryStorage::new();
+        high_scores.save(&mut storage, "scores").unwrap();
+        let loaded = load(&storage).unwrap();
+        assert_eq!(loaded.entries(), high_scores.entries());
+
+        // An empty slot is an empty table
+        assert!(
+            HighScores::load(&storage, "other", 10, ScoreOrder::HighestFirst)
+                .unwrap()
+                .entries()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn equal_scores_rank_below_existing_ones() {
+        let mut high_scores = HighScores::new(10, ScoreOrder::LowestFirst);
+        assert_eq!(high_scores.insert(entry("Ada", 12.5)), Some(0));
+        assert_eq!(high_scores.insert(entry("Bob", 12.5)), Some(1));
+        assert_eq!(high_scores.insert(entry("Cy", 9.0)), Some(0));
+        assert_eq!(names(&high_scores), ["Cy", "Ada", "Bob"]);
+    }
+
+    #[test]
+    fn tables_are_truncated_to_max_entries() {
+        let mut high_scores = HighScores::new(3, ScoreOrder::HighestFirst);
+        for (name, score) in [("A", 1.0), ("B", 2.0), ("C", 3.0), ("D", 4.0)] {
+            high_scores.insert(entry(name, score));
+        }
+        assert_eq!(names(&high_scores), ["D", "C", "B"]);
+
+        assert!(!high_scores.qualifies(2.0));
+        assert!(!high_scores.qualifies(Float::NAN));
+        assert_eq!(high_scores.insert(entry("E", 2.0)), None);
+        assert_eq!(high_scores.insert(entry("F", 2.5)), Some(2));
+
+        let storage = stored(FILE_VERSION, high_scores.entries().to_vec());
+        let loaded = HighScores::load(&storage, "scores", 2, ScoreOrder::HighestFirst).unwrap();
+        assert_eq!(names(&loaded), ["D", "C"]);
+    }
+
+    #[test]
+    fn edited_files_fail_the_checksum() {
+        let storage = stored(FILE_VERSION, vec![entry("Ada", 30.0)]);
+        let data = storage.read("scores").unwrap().unwrap();
+        let mut storage = MemoryStorage::new();
+        storage
+            .write("scores", &data.replace("30.0", "99.0"))
+            .unwrap();
+
+        assert!(is_invalid(load(&storage)));
+    }
+
+    #[test]
+    fn malformed_files_are_invalid() {
+        let mut storage = MemoryStorage::new();
+        storage.write("scores", "{ not json").unwrap();
+        assert!(is_invalid(load(&storage)));
+
+        // Out of range numbers would be infinite scores
+        let file = r#"{"version": 1, "checksum": "0", "entries": [
+            {"name": "Ada", "score": 1e999, "date": 0, "metadata": {}}]}"#;
+        storage.write("scores", file).unwrap();
+        assert!(is_invalid(load(&storage)));
+    }
+
+    #[test]
+    fn other_versions_are_invalid() {
+        let storage = stored(FILE_VERSION + 1, vec![entry("Ada", 30.0)]);
+        assert!(is_invalid(load(&storage)));
+    }
+
+    #[test]
+    fn entries_out_of_order_are_invalid() {
+        let storage = stored(FILE_VERSION, vec![entry("Ada", 10.0), entry("Bob", 20.0)]);
+        assert!(is_invalid(load(&storage)));
+
+        let loaded = HighScores::load(&storage, "scores", 10, ScoreOrder::LowestFirst).unwrap();
+        assert_eq!(names(&loaded), ["Ada", "Bob"]);
+    }
+
+    #[test]
+    fn long_names_and_non_finite_scores_are_invalid() {
+        let long_name = ScoreEntry {
+            name: "x".repeat(MAX_NAME_CHARS + 1),
+            ..entry("", 10.0)
+        };
+        assert!(is_invalid(load(&stored(FILE_VERSION, vec![long_name]))));
+
+        // Stored as null, which isn't a number
+        let storage = stored(FILE_VERSION, vec![entry("Ada", Float::INFINITY)]);
+        assert!(is_invalid(load(&storage)));
+
+        // Names are cut to the limit when entries are created
+        assert_eq!(
+            ScoreEntry::new(&"x".repeat(40), 1.0).name.len(),
+            MAX_NAME_CHARS
+        );
+    }
+
+    #[test]
+    fn dates_are_shown_in_utc() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+        assert_eq!(civil_date(19_723), (2024, 1, 1));
+
+        let entry = ScoreEntry {
+            date: 19_723 * 86_400 + 86_399,
+            ..entry("Ada", 1.0)
+        };
+        assert_eq!(entry.date_string(), "2024-01-01");
+    }
+}
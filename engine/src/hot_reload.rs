@@ -0,0 +1,292 @@
+//! Desktop development mode that loads the `Game` from a dynamic library and swaps in the
+//! new build whenever the library file changes. Game state is carried over through serde.
+//!
+//! The game crate is built as a `cdylib` (`crate-type = ["cdylib", "rlib"]` keeps the
+//! normal static build working) and exports its game with
+//!
+//! ```ignore
+//! engine::export_game!(MyGame, MyGame::new());
+//! ```
+//!
+//! where `MyGame` implements `Serialize` and `Deserialize`. The host binary then runs
+//!
+//! ```ignore
+//! let game = HotReload::new(hot_reload::library_path("my_game"));
+//! engine::create(game, String::from("My Game")).start();
+//! ```
+//!
+//! and `cargo build -p my_game` while it runs picks up the changes. State that no longer
+//! serializes or deserializes after a change, e.g. because a field was added, restarts the
+//! game from the constructor given to `export_game!`. Each reload publishes a `Reloaded`
+//! event and problems are published as `ReloadError` events, see `Engine::subscribe`.
+//!
+//! The library has to be built with the same compiler and engine features as the host.
+//! Nothing created by the library code may outlive it, so `Engine::subscribe` handlers
+//! and assets of types defined in the game crate aren't supported in this mode.
+
+use crate::{Engine, Event, Float, Game, UserEvent};
+use libloading::Library;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Name of the function generated by `export_game!`
+const CREATE_SYMBOL: &[u8] = b"engine_create_game";
+/// Waiting for the file to settle avoids loading a library the linker is still writing
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Creates the game from the saved state, also returning why the state wasn't restored
+type CreateGame = fn(Option<&str>) -> (Box<dyn ReloadableGame>, Option<String>);
+
+/// Published after the game library was reloaded
+#[derive(Clone, Debug)]
+pub struct Reloaded {
+    pub path: PathBuf,
+}
+
+/// Published when the library couldn't be reloaded, or the game state didn't survive
+#[derive(Clone, Debug)]
+pub struct ReloadError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+/// Game whose state survives reloads, implemented for every serializable `Game`
+pub trait ReloadableGame: Game {
+    fn save_state(&self) -> Result<String, String>;
+}
+
+impl<G: Game + Serialize> ReloadableGame for G {
+    fn save_state(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|error| error.to_string())
+    }
+}
+
+/// Exports `$game` from a game library, `$create` makes the game when there's no state to
+/// restore
+#[macro_export]
+macro_rules! export_game {
+    ($game:ty, $create:expr) => {
+        #[unsafe(no_mangle)]
+        pub fn engine_create_game(
+            state: Option<&str>,
+        ) -> (Box<dyn $crate::hot_reload::ReloadableGame>, Option<String>) {
+            let (game, error): ($game, _) = match $crate::hot_reload::restore(state) {
+                Ok(Some(game)) => (game, None),
+                Ok(None) => ($create, None),
+                Err(error) => ($create, Some(error)),
+            };
+            (Box::new(game), error)
+        }
+    };
+}
+
+/// Deserializes the state saved before a reload, used by `export_game!`
+pub fn restore<G: DeserializeOwned>(state: Option<&str>) -> Result<Option<G>, String> {
+    let Some(state) = state else {
+        return Ok(None);
+    };
+    serde_json::from_str(state)
+        .map(Some)
+        .map_err(|error| format!("Game state couldn't be restored, starting over: {}", error))
+}
+
+/// Path of the library built from the crate `name`, next to the running executable
+pub fn library_path(name: &str) -> PathBuf {
+    let executable = std::env::current_exe().unwrap();
+    let directory = executable.parent().unwrap();
+    directory.join(format!(
+        "{}{}{}",
+        std::env::consts::DLL_PREFIX,
+        name,
+        std::env::consts::DLL_SUFFIX
+    ))
+}
+
+/// Library currently in use, loaded from a copy so the build can overwrite the original
+struct Loaded {
+    library: Library,
+    copy_path: PathBuf,
+}
+
+impl Drop for Loaded {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.copy_path);
+    }
+}
+
+/// `Game` loaded from a library built with `export_game!` and reloaded when it changes
+pub struct HotReload {
+    path: PathBuf,
+    // Declared before `loaded` so the game is dropped before its code is unloaded
+    game: Option<Box<dyn ReloadableGame>>,
+    loaded: Option<Loaded>,
+    modified: Option<SystemTime>,
+    load_count: u32,
+}
+
+impl HotReload {
+    /// Loads the game library at `path`, panics if it can't be loaded
+    pub fn new(path: impl Into<PathBuf>) -> HotReload {
+        let mut hot_reload = HotReload {
+            path: path.into(),
+            game: None,
+            loaded: None,
+            modified: None,
+            load_count: 0,
+        };
+
+        hot_reload.modified = modified_time(&hot_reload.path);
+        hot_reload.reload().unwrap();
+        hot_reload
+    }
+
+    fn reload_if_changed(&mut self, engine: &Engine) {
+        let modified = modified_time(&self.path);
+        let settled = modified
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|elapsed| elapsed >= SETTLE_TIME);
+        if modified == self.modified || !settled {
+            return;
+        }
+        self.modified = modified;
+
+        let path = self.path.clone();
+        let messages = match self.reload() {
+            Ok(state_errors) => {
+                engine.publish(Reloaded { path: path.clone() });
+                state_errors
+            }
+            Err(message) => vec![message],
+        };
+        for message in messages {
+            engine.publish(ReloadError {
+                path: path.clone(),
+                message,
+            });
+        }
+    }
+
+    /// Loads the current library, only replacing the running game once that succeeded.
+    /// Returns why the game state was lost, if it was.
+    fn reload(&mut self) -> Result<Vec<String>, String> {
+        self.load_count += 1;
+        let copy_path = std::env::temp_dir().join(format!(
+            "{}-{}-{}",
+            std::process::id(),
+            self.load_count,
+            self.path.file_name().unwrap().to_string_lossy()
+        ));
+        std::fs::copy(&self.path, &copy_path).map_err(|error| error.to_string())?;
+
+        // SAFETY: the library is built with `export_game!`, which defines the symbol with
+        // the expected signature, and by the same compiler as the engine
+        let library = match unsafe { Library::new(&copy_path) } {
+            Ok(library) => library,
+            Err(error) => {
+                let _ = std::fs::remove_file(&copy_path);
+                return Err(error.to_string());
+            }
+        };
+        let loaded = Loaded { library, copy_path };
+        let create: CreateGame = *unsafe { loaded.library.get::<CreateGame>(CREATE_SYMBOL) }
+            .map_err(|error| error.to_string())?;
+
+        let mut state_errors = Vec::new();
+        let state = match self.game.take().map(|game| game.save_state()) {
+            Some(Ok(state)) => Some(state),
+            Some(Err(error)) => {
+                state_errors.push(format!(
+                    "Game state couldn't be saved, starting over: {}",
+                    error
+                ));
+                None
+            }
+            None => None,
+        };
+        let (game, error) = create(state.as_deref());
+        state_errors.extend(error);
+        self.game = Some(game);
+        self.loaded = Some(loaded);
+
+        Ok(state_errors)
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl Game for HotReload {
+    fn handle_event(&mut self, event: Event) -> Option<Event> {
+        self.game.as_mut().unwrap().handle_event(event)
+    }
+
+    fn update(&mut self, dt: Float, engine: &Engine) {
+        self.game.as_mut().unwrap().update(dt, engine);
+    }
+
+    fn render(&mut self, engine: &mut Engine) {
+        // Checked here rather than in `update` so reloading also works while paused
+        self.reload_if_changed(engine);
+        self.game.as_mut().unwrap().render(engine);
+    }
+
+    fn handle_user_event(&mut self, event: &UserEvent, engine: &mut Engine) {
+        self.game.as_mut().unwrap().handle_user_event(event, engine);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Default, Serialize, Deserialize)]
+    struct TestGame {
+        score: u32,
+        visited: BTreeMap<(i32, i32), bool>,
+    }
+
+    impl Game for TestGame {
+        fn update(&mut self, _dt: Float, _engine: &Engine) {}
+        fn render(&mut self, _engine: &mut Engine) {}
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let game = TestGame {
+            score: 7,
+            ..TestGame::default()
+        };
+        let state = game.save_state().unwrap();
+        let restored: TestGame = restore(Some(&state)).unwrap().unwrap();
+        assert_eq!(restored.score, 7);
+    }
+
+    #[test]
+    fn state_json_cant_represent_is_an_error() {
+        let mut game = TestGame::default();
+        game.visited.insert((1, 2), true);
+        assert!(game.save_state().is_err());
+    }
+
+    #[test]
+    fn restore_without_state_starts_over() {
+        assert!(restore::<TestGame>(None).unwrap().is_none());
+        assert!(restore::<TestGame>(Some(r#"{"score": "high"}"#)).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use sdl2::keyboard::Keycode;
+
+// Defines the `Key` enum together with its mapping to SDL keycodes.
+// Engine key names follow the SDL ones wherever possible.
+macro_rules! keys {
+    ($($key:ident => $keycode:ident),* $(,)?) => {
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($key,)*
+        }
+
+        impl Key {
+            /// Looks a key up by its variant name, e.g. "Space" or "F1"
+            pub fn from_name(name: &str) -> Option<Key> {
+                match name {
+                    $(stringify!($key) => Some(Key::$key),)*
+                    _ => None,
+                }
+            }
+
+            pub(crate) fn from_sdl(keycode: Keycode) -> Option<Key> {
+                match keycode {
+                    $(Keycode::$keycode => Some(Key::$key),)*
+                    _ => None,
+                }
+            }
+
+            pub(crate) fn to_sdl(self) -> Keycode {
+                match self {
+                    $(Key::$key => Keycode::$keycode,)*
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    A => A, B => B, C => C, D => D, E => E, F => F, G => G, H => H, I => I,
+    J => J, K => K, L => L, M => M, N => N, O => O, P => P, Q => Q, R => R,
+    S => S, T => T, U => U, V => V, W => W, X => X, Y => Y, Z => Z,
+    Num0 => Num0, Num1 => Num1, Num2 => Num2, Num3 => Num3, Num4 => Num4,
+    Num5 => Num5, Num6 => Num6, Num7 => Num7, Num8 => Num8, Num9 => Num9,
+    F1 => F1, F2 => F2, F3 => F3, F4 => F4, F5 => F5, F6 => F6,
+    F7 => F7, F8 => F8, F9 => F9, F10 => F10, F11 => F11, F12 => F12,
+    Up => Up, Down => Down, Left => Left, Right => Right,
+    Escape => Escape, Return => Return, Space => Space, Tab => Tab,
+    Backspace => Backspace, Delete => Delete, Insert => Insert,
+    Home => Home, End => End, PageUp => PageUp, PageDown => PageDown,
+    LShift => LShift, RShift => RShift, LCtrl => LCtrl, RCtrl => RCtrl,
+    LAlt => LAlt, RAlt => RAlt,
+    Minus => Minus, Equals => Equals, Comma => Comma, Period => Period,
+    Slash => Slash, Backslash => Backslash, Semicolon => Semicolon, Quote => Quote,
+    LeftBracket => LeftBracket, RightBracket => RightBracket, Backquote => Backquote,
+    Pause => Pause, PrintScreen => PrintScreen,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    X1,
+    X2,
+}
+
+impl MouseButton {
+    pub(crate) fn from_sdl(button: sdl2::mouse::MouseButton) -> Option<MouseButton> {
+        match button {
+            sdl2::mouse::MouseButton::Left => Some(MouseButton::Left),
+            sdl2::mouse::MouseButton::Middle => Some(MouseButton::Middle),
+            sdl2::mouse::MouseButton::Right => Some(MouseButton::Right),
+            sdl2::mouse::MouseButton::X1 => Some(MouseButton::X1),
+            sdl2::mouse::MouseButton::X2 => Some(MouseButton::X2),
+            sdl2::mouse::MouseButton::Unknown => None,
+        }
+    }
+}
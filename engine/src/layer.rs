@@ -0,0 +1,68 @@
+use crate::{dimensions, font, Color, Dimensions, FrameRect, Point, SpriteSheet};
+use sdl2::render::{BlendMode, Texture, WindowCanvas};
+
+/// Handle to an offscreen render target created with `Engine::create_layer`.
+/// Layer contents persist between frames until drawn over.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Layer(pub(crate) usize);
+
+/// Drawing surface passed to `Engine::draw_on_layer`. Coordinates are in points relative to the layer.
+pub struct LayerCanvas<'a> {
+    pub(crate) canvas: &'a mut WindowCanvas,
+    pub(crate) dimensions: &'a Dimensions,
+    /// The engine's textures, with the layer being drawn on taken out
+    pub(crate) textures: &'a [Option<Texture>],
+}
+
+impl LayerCanvas<'_> {
+    pub fn draw_point(&mut self, position: impl Into<Point>, color: Color) {
+        self.canvas.set_draw_color(color);
+
+        let draw_rect = dimensions::point_at(self.dimensions, position.into());
+        self.canvas.fill_rect(draw_rect).unwrap()
+    }
+
+    pub fn draw_rect(&mut self, position: impl Into<Point>, width: u32, height: u32, color: Color) {
+        self.canvas.set_draw_color(color);
+
+        let draw_rect = dimensions::rect_at(self.dimensions, position.into(), width, height);
+        self.canvas.fill_rect(draw_rect).unwrap()
+    }
+
+    /// Draws part of a sprite sheet, see `Engine::draw_sprite`. Panics if `sheet` is the
+    /// layer being drawn on.
+    pub fn draw_sprite(
+        &mut self,
+        sheet: SpriteSheet,
+        frame: FrameRect,
+        position: impl Into<Point>,
+    ) {
+        let texture = self.textures[sheet.0]
+            .as_ref()
+            .expect("Sprite sheet was destroyed or is the layer drawn on");
+        let draw_rect =
+            dimensions::rect_at(self.dimensions, position.into(), frame.width, frame.height);
+
+        self.canvas
+            .copy(texture, Some(frame.into()), draw_rect)
+            .unwrap();
+    }
+
+    /// Draws `text` with the built-in font, see `Engine::draw_text`
+    pub fn draw_text(&mut self, text: &str, position: impl Into<Point>, color: Color) {
+        font::draw_text(self.canvas, self.dimensions, text, position.into(), color);
+    }
+
+    /// Makes the area fully transparent again
+    pub fn erase_rect(&mut self, position: impl Into<Point>, width: u32, height: u32) {
+        // Without blending the transparent color replaces the pixels instead of mixing in
+        self.canvas.set_blend_mode(BlendMode::None);
+        self.draw_rect(position, width, height, Color::TRANSPARENT);
+        self.canvas.set_blend_mode(BlendMode::Blend);
+    }
+
+    pub fn clear(&mut self) {
+        self.canvas.set_draw_color(Color::TRANSPARENT);
+        self.canvas.clear();
+    }
+}
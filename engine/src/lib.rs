@@ -3,58 +3,353 @@
 #[cfg(target_family = "wasm")]
 pub mod emscripten;
 
+mod animation;
+pub mod assets;
+mod color;
+pub mod config;
+mod cursor;
 mod dimensions;
+#[cfg(feature = "ecs")]
+pub mod ecs;
 mod engine_builder;
+mod event;
+mod event_bus;
+pub mod font;
 mod game;
-mod point;
-mod time;
+pub mod geom;
+pub mod highscores;
+#[cfg(all(feature = "hot-reload", not(target_family = "wasm")))]
+pub mod hot_reload;
+mod input;
+mod layer;
+#[cfg(all(feature = "net", not(target_family = "wasm")))]
+pub mod net;
+pub mod particles;
+pub mod physics;
+mod plugin;
+pub mod save;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+mod sprite;
+mod text_input;
+#[cfg(feature = "tiled")]
+pub mod tiled;
+mod tilemap;
+pub mod time;
+pub mod ui;
+mod vec2;
+mod window;
 
+pub use animation::{Animation, AnimationFrame, AnimationPlayer, Animator, PlayMode};
+pub use color::Color;
 pub use dimensions::Dimensions;
 pub use engine_builder::create;
+pub use event::Event;
+pub use event_bus::{Subscription, UserEvent};
 pub use game::Game;
-pub use point::Point;
+pub use input::{Key, MouseButton};
+pub use layer::{Layer, LayerCanvas};
+pub use plugin::{Plugin, QuitOnEscape};
+pub use sprite::{FrameRect, SpriteSheet};
+pub use text_input::TextBuffer;
+pub use tilemap::{Direction, TileMap};
+pub use vec2::{Point, Vec2};
 
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::Duration;
 
-use sdl2::event::Event;
-use sdl2::keyboard::{Keycode, Scancode};
-use sdl2::pixels::Color;
-use sdl2::render::WindowCanvas;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{BlendMode, Texture, WindowCanvas};
+use sdl2::surface::Surface;
 
 pub type Float = f64;
 
 pub struct Engine {
     running: bool,
+    // Dropped before `sdl`
+    cursor: Option<sdl2::mouse::Cursor>,
+    cursor_image: Option<cursor::CursorImage>,
+    sdl: sdl2::Sdl,
     event_pump: sdl2::EventPump,
     canvas: WindowCanvas,
+    textures: Vec<Option<Texture>>,
     game: Rc<RefCell<dyn Game>>,
-    time: Duration,
+    clock: time::Clock,
     dimensions: Dimensions,
     background_color: Color,
+    assets: assets::Assets,
+    bindings: HashMap<String, Vec<Key>>,
+    seed: u64,
+    target_fps: u32,
+    event_bus: event_bus::EventBus,
+    plugins: Vec<Box<dyn Plugin>>,
+    scaling: config::ScalingMode,
+    /// Set when the window placement is stored on exit
+    remembered_window_title: Option<String>,
 }
 
 // API
 impl Engine {
-    pub fn draw_point(&mut self, x: i32, y: i32, color: Color) {
+    pub fn dimensions(&self) -> &Dimensions {
+        &self.dimensions
+    }
+
+    pub fn draw_point(&mut self, position: impl Into<Point>, color: Color) {
         self.canvas.set_draw_color(color);
 
-        let draw_rect = dimensions::point_at(&self.dimensions, x, y);
+        let draw_rect = dimensions::point_at(&self.dimensions, position.into());
         self.canvas.fill_rect(draw_rect).unwrap()
     }
 
-    pub fn draw_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+    pub fn draw_rect(&mut self, position: impl Into<Point>, width: u32, height: u32, color: Color) {
         self.canvas.set_draw_color(color);
 
-        let draw_rect = dimensions::rect_at(&self.dimensions, x, y, width, height);
+        let draw_rect = dimensions::rect_at(&self.dimensions, position.into(), width, height);
         self.canvas.fill_rect(draw_rect).unwrap()
     }
 
-    pub fn is_key_pressed(&self, keycode: Keycode) -> bool {
+    pub fn fill_rect(&mut self, rect: &geom::Rect, color: Color) {
+        let (position, width, height) = rect.to_points();
+        self.draw_rect(position, width, height, color);
+    }
+
+    pub fn fill_circle(&mut self, circle: &geom::Circle, color: Color) {
+        let (min, width, height) = circle.bounds().to_points();
+
+        // Fill every point whose center lies inside the circle
+        for y in min.y..=min.y + height as i32 {
+            for x in min.x..=min.x + width as i32 {
+                let point = Point::new(x, y);
+                if circle.contains_point(point.to_float() + Vec2::new(0.5, 0.5)) {
+                    self.draw_point(point, color);
+                }
+            }
+        }
+    }
+
+    /// Draws `text` with the built-in 5x7 font, one point per font pixel, with the top-left
+    /// corner at `position`. Lines are separated by '\n', see `font::text_size`.
+    pub fn draw_text(&mut self, text: &str, position: impl Into<Point>, color: Color) {
+        font::draw_text(
+            &mut self.canvas,
+            &self.dimensions,
+            text,
+            position.into(),
+            color,
+        );
+    }
+
+    /// Creates a transparent offscreen layer, sized in points
+    pub fn create_layer(&mut self, width: u32, height: u32) -> Layer {
+        let point_size = self.dimensions.point_size();
+        let mut texture = self
+            .canvas
+            .create_texture_target(
+                PixelFormatEnum::ARGB8888,
+                width * point_size,
+                height * point_size,
+            )
+            .unwrap();
+        texture.set_blend_mode(BlendMode::Blend);
+
+        let layer = Layer(self.textures.len());
+        self.textures.push(Some(texture));
+        self.draw_on_layer(layer, |canvas| canvas.clear());
+
+        layer
+    }
+
+    pub fn draw_on_layer(&mut self, layer: Layer, draw: impl FnOnce(&mut LayerCanvas)) {
+        // Taken out while drawing, so sprite sheets can be read from the other textures
+        let mut texture = self.textures[layer.0].take().expect("Layer was destroyed");
+        let dimensions = &self.dimensions;
+        let textures = &self.textures;
+
+        self.canvas
+            .with_texture_canvas(&mut texture, |canvas| {
+                draw(&mut LayerCanvas {
+                    canvas,
+                    dimensions,
+                    textures,
+                });
+            })
+            .unwrap();
+        self.textures[layer.0] = Some(texture);
+    }
+
+    /// Draws the layer contents with the top-left corner at `position`
+    pub fn draw_layer(&mut self, layer: Layer, position: impl Into<Point>) {
+        let texture = self.textures[layer.0]
+            .as_ref()
+            .expect("Layer was destroyed");
+        let query = texture.query();
+        let position = position.into();
+        let point_size = self.dimensions.point_size() as i32;
+        let draw_rect = sdl2::rect::Rect::new(
+            position.x * point_size,
+            position.y * point_size,
+            query.width,
+            query.height,
+        );
+
+        self.canvas.copy(texture, None, draw_rect).unwrap();
+    }
+
+    pub fn destroy_layer(&mut self, layer: Layer) {
+        self.destroy_texture(layer.0);
+    }
+
+    /// Loads a BMP image as a sprite sheet
+    pub fn load_sprite_sheet(&mut self, path: impl AsRef<Path>) -> Result<SpriteSheet, String> {
+        let surface = Surface::load_bmp(path)?;
+        let texture = self
+            .canvas
+            .create_texture_from_surface(&surface)
+            .map_err(|error| error.to_string())?;
+
+        Ok(self.add_sprite_sheet(texture))
+    }
+
+    /// Creates a sprite sheet from pixels given row by row
+    pub fn create_sprite_sheet(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: &[Color],
+    ) -> SpriteSheet {
+        assert_eq!(
+            pixels.len(),
+            (width * height) as usize,
+            "Pixel count doesn't match the size"
+        );
+
+        let bytes: Vec<u8> = pixels
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b, color.a])
+            .collect();
+
+        let mut texture = self
+            .canvas
+            .create_texture_static(PixelFormatEnum::RGBA32, width, height)
+            .unwrap();
+        texture.update(None, &bytes, width as usize * 4).unwrap();
+
+        self.add_sprite_sheet(texture)
+    }
+
+    /// Sheet size in pixels
+    pub fn sprite_sheet_size(&self, sheet: SpriteSheet) -> (u32, u32) {
+        let query = self.textures[sheet.0]
+            .as_ref()
+            .expect("Sprite sheet was destroyed")
+            .query();
+        (query.width, query.height)
+    }
+
+    /// Draws the `frame` part of the sheet with its top-left corner at `position`.
+    /// Every sheet pixel covers one point.
+    pub fn draw_sprite(
+        &mut self,
+        sheet: SpriteSheet,
+        frame: FrameRect,
+        position: impl Into<Point>,
+    ) {
+        let texture = self.textures[sheet.0]
+            .as_ref()
+            .expect("Sprite sheet was destroyed");
+        let draw_rect =
+            dimensions::rect_at(&self.dimensions, position.into(), frame.width, frame.height);
+
+        self.canvas
+            .copy(texture, Some(frame.into()), draw_rect)
+            .unwrap();
+    }
+
+    pub fn destroy_sprite_sheet(&mut self, sheet: SpriteSheet) {
+        self.destroy_texture(sheet.0);
+    }
+
+    pub fn is_key_pressed(&self, key: Key) -> bool {
         self.event_pump
             .keyboard_state()
-            .is_scancode_pressed(Scancode::from_keycode(keycode).unwrap())
+            .is_scancode_pressed(Scancode::from_keycode(key.to_sdl()).unwrap())
+    }
+
+    /// True while any key bound to `action` in the config is pressed
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.action_keys(action)
+            .iter()
+            .any(|&key| self.is_key_pressed(key))
+    }
+
+    pub fn action_keys(&self, action: &str) -> &[Key] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Seed from the config, or a random one picked at startup
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Real time elapsed since the engine was created
+    pub fn elapsed_time(&self) -> Duration {
+        self.clock.elapsed()
+    }
+
+    /// Number of frames run so far
+    pub fn frame_count(&self) -> u64 {
+        self.clock.frame_count()
+    }
+
+    pub fn time_scale(&self) -> Float {
+        self.clock.time_scale()
+    }
+
+    /// Multiplier applied to the `dt` passed to `Game::update`
+    pub fn set_time_scale(&mut self, time_scale: Float) {
+        self.clock.set_time_scale(time_scale);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    /// Stops calling `Game::update` while still rendering every frame.
+    /// Also toggled with the keys bound to the "pause" action.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.clock.set_paused(paused);
+    }
+
+    /// Runs exactly one update on the next frame while paused, with `dt` of one frame at
+    /// the target frame rate. Also triggered with the keys bound to the "step" action.
+    pub fn step(&mut self) {
+        self.clock
+            .step(Duration::from_secs(1) / self.target_fps.max(1));
+    }
+
+    /// Stops the main loop after the current frame
+    pub fn quit(&mut self) {
+        self.running = false;
+    }
+}
+
+// Texture bookkeeping
+impl Engine {
+    fn add_sprite_sheet(&mut self, mut texture: Texture) -> SpriteSheet {
+        texture.set_blend_mode(BlendMode::Blend);
+        self.textures.push(Some(texture));
+        SpriteSheet(self.textures.len() - 1)
+    }
+
+    fn destroy_texture(&mut self, index: usize) {
+        if let Some(texture) = self.textures[index].take() {
+            // SAFETY: the texture was created by this engine's canvas, which is still alive
+            unsafe { texture.destroy() };
+        }
     }
 }
 
@@ -63,33 +358,112 @@ impl Engine {
     pub fn new(
         game: Rc<RefCell<dyn Game>>,
         game_title: String,
-        dimensions: Dimensions,
+        mut dimensions: Dimensions,
         background_color: Color,
+        time_source: Box<dyn time::TimeSource>,
+        asset_root: PathBuf,
+        config: config::EngineConfig,
     ) -> Engine {
+        if config.headless {
+            sdl2::hint::set("SDL_VIDEODRIVER", "dummy");
+        }
+
         let sdl = sdl2::init().unwrap();
         let video = sdl.video().unwrap();
+        // SDL starts with text input enabled on desktop, games opt in with `start_text_input`
+        video.text_input().stop();
 
-        let window = video
-            .window(
-                game_title.as_str(),
-                dimensions.pixel_width(),
-                dimensions.pixel_height(),
-            )
-            .position_centered()
-            .build()
-            .unwrap();
+        let mut window = video.window(
+            game_title.as_str(),
+            dimensions.pixel_width(),
+            dimensions.pixel_height(),
+        );
+        window.allow_highdpi();
 
-        let canvas = window.into_canvas().build().unwrap();
+        // A remembered position is only used if it's still on one of the displays
+        let remembered = config
+            .remember_window
+            .then(|| window::WindowState::load(&game_title))
+            .flatten()
+            .filter(|state| {
+                (0..video.num_video_displays().unwrap_or(0)).any(|display| {
+                    video
+                        .display_bounds(display)
+                        .is_ok_and(|bounds| bounds.contains_point((state.x, state.y)))
+                })
+            });
+        match remembered {
+            Some(state) => {
+                window.position(state.x, state.y);
+            }
+            None => {
+                let centered = (sdl2::sys::SDL_WINDOWPOS_CENTERED_MASK | config.display) as i32;
+                window.position(centered, centered);
+            }
+        }
+        if config.borderless {
+            window.borderless();
+        }
+        if config.always_on_top {
+            window.always_on_top();
+        }
+        if config.fullscreen {
+            window.fullscreen_desktop();
+        }
+        if config.headless {
+            window.hidden();
+        }
+
+        let mut window = window.build().unwrap();
+
+        // HiDPI windows are sized in units covering several pixels. Points are whole
+        // pixels, so the window is shrunk to keep the requested pixel size.
+        let pixel_ratio = window.drawable_size().0 as Float / window.size().0.max(1) as Float;
+        dimensions.set_pixel_ratio(pixel_ratio);
+        if pixel_ratio != 1.0 && !config.fullscreen {
+            window
+                .set_size(dimensions.window_width(), dimensions.window_height())
+                .unwrap();
+        }
+
+        let mut canvas = window.into_canvas();
+        if config.vsync {
+            canvas = canvas.present_vsync();
+        }
+        let mut canvas = canvas.build().unwrap();
+
+        let mut clock = time::Clock::new(time_source);
+        clock.set_time_scale(config.time_scale);
+        clock.set_paused(config.paused);
+
+        let mut bindings = config.bindings;
+        bindings
+            .entry("pause".to_string())
+            .or_insert(vec![Key::Pause]);
+        bindings.entry("step".to_string()).or_insert(vec![Key::F10]);
+        canvas.set_blend_mode(BlendMode::Blend);
         let event_pump = sdl.event_pump().unwrap();
 
         Engine {
             running: true,
+            cursor: None,
+            cursor_image: None,
+            sdl,
             event_pump,
             canvas,
+            textures: Vec::new(),
             game,
-            time: time::now(),
+            clock,
             dimensions,
             background_color,
+            assets: assets::Assets::new(asset_root),
+            bindings,
+            seed: config.seed.unwrap_or_else(rand::random),
+            target_fps: config.target_fps,
+            event_bus: event_bus::EventBus::default(),
+            plugins: Vec::new(),
+            scaling: config::ScalingMode::Fixed,
+            remembered_window_title: config.remember_window.then_some(game_title),
         }
     }
 
@@ -103,6 +477,7 @@ impl Engine {
         Engine::start_desktop(engine);
     }
 
+    /// The browser paces the web build, so `target_fps` only applies on desktop
     #[cfg(target_family = "wasm")]
     fn start_emscripten(engine: Rc<RefCell<Engine>>) {
         // Set the canvas size to match the pixel dimensions
@@ -113,6 +488,7 @@ impl Engine {
                 dimensions.pixel_height(),
             );
         }
+        emscripten::watch_resize();
 
         emscripten::set_main_loop_callback(Engine::create_main_loop(engine));
     }
@@ -120,10 +496,24 @@ impl Engine {
     #[cfg(not(target_family = "wasm"))]
     fn start_desktop(engine: Rc<RefCell<Engine>>) {
         let mut loop_func = Engine::create_main_loop(engine.clone());
+        let target_fps = engine.borrow().target_fps;
+        let frame_time = Duration::from_secs(1) / target_fps.max(1);
 
         while engine.borrow().running {
+            let frame_start = std::time::Instant::now();
             loop_func();
-            std::thread::sleep(Duration::from_millis(16));
+
+            // Sleep off the rest of the frame
+            if let Some(remaining) = frame_time.checked_sub(frame_start.elapsed()) {
+                std::thread::sleep(remaining);
+            }
+        }
+
+        // Published like any other event, so the game can report it before exiting
+        let engine = &mut *engine.borrow_mut();
+        if let Err(error) = engine.remember_window() {
+            engine.publish(error);
+            engine.deliver_user_events();
         }
     }
 
@@ -132,35 +522,81 @@ impl Engine {
 
         move || {
             let engine = &mut *engine.borrow_mut();
-            let event_pump = &mut engine.event_pump;
-
-            for event in event_pump.poll_iter() {
-                if let Some(event) = engine.game.borrow_mut().handle_event(event) {
-                    match event {
-                        Event::Quit { .. }
-                        | Event::KeyDown {
-                            keycode: Some(Keycode::Escape),
-                            ..
-                        } => {
-                            engine.running = false;
-                        }
-                        _ => (),
+            // Plugins get the engine mutably, so they are taken out for the frame
+            let mut plugins = std::mem::take(&mut engine.plugins);
+
+            #[allow(unused_mut)]
+            let mut events: Vec<Event> = engine
+                .event_pump
+                .poll_iter()
+                .filter_map(|event| Event::from_sdl(event, &engine.dimensions))
+                .collect();
+
+            #[cfg(target_family = "wasm")]
+            if emscripten::take_resize() {
+                events.extend(engine.resize_to_browser());
+            }
+
+            for event in events {
+                // Debug keys are handled by the engine and not passed to the game
+                if let Event::KeyDown {
+                    key: Some(key),
+                    repeat: false,
+                } = event
+                {
+                    if engine.bindings["pause"].contains(&key) {
+                        engine.set_paused(!engine.is_paused());
+                        continue;
+                    }
+                    if engine.bindings["step"].contains(&key) {
+                        engine.step();
+                        continue;
                     }
                 }
+
+                let event = plugins
+                    .iter_mut()
+                    .try_fold(event, |event, plugin| plugin.before_event(event, engine))
+                    .and_then(|event| game.borrow_mut().handle_event(event))
+                    .and_then(|event| {
+                        plugins
+                            .iter_mut()
+                            .try_fold(event, |event, plugin| plugin.after_event(event, engine))
+                    });
+
+                if let Some(Event::Quit) = event {
+                    engine.running = false;
+                }
             }
 
-            let now = time::now();
-            let dt = now - engine.time;
-            engine.time = now;
+            #[cfg(not(target_family = "wasm"))]
+            engine.reload_changed_assets();
 
-            game.borrow_mut().update(dt.as_secs_f64() as Float, engine);
+            if let Some(dt) = engine.clock.tick() {
+                for plugin in &mut plugins {
+                    plugin.before_update(dt, engine);
+                }
+                game.borrow_mut().update(dt, engine);
+                for plugin in &mut plugins {
+                    plugin.after_update(dt, engine);
+                }
+            }
+
+            engine.deliver_user_events();
 
             engine.canvas.set_draw_color(engine.background_color);
             engine.canvas.clear();
 
+            for plugin in &mut plugins {
+                plugin.before_render(engine);
+            }
             game.borrow_mut().render(engine);
+            for plugin in &mut plugins {
+                plugin.after_render(engine);
+            }
 
             engine.canvas.present();
+            engine.plugins = plugins;
         }
     }
 }
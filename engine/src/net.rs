@@ -0,0 +1,488 @@
+//! Deterministic lockstep multiplayer over UDP.
+//!
+//! Every peer runs the whole simulation. Each tick the local player's input is sampled with
+//! `NetGame::local_input` and sent to all peers, and `Game::update` only runs once the
+//! inputs of every player for that tick have arrived. Inputs are scheduled `input_delay`
+//! ticks ahead, which hides the network latency as long as it stays below the delay.
+//!
+//! The simulation has to be deterministic: game state may only change in `update`, based
+//! on the inputs from `NetGame::set_inputs` and the fixed `dt`. Randomness should come
+//! from a generator seeded the same way on all peers.
+//!
+//! Two processes on one machine play together with
+//!
+//! ```ignore
+//! // First process, player 0
+//! let game = Lockstep::new(MyGame::new(), "127.0.0.1:4000", 0)
+//!     .unwrap()
+//!     .with_peer(1, "127.0.0.1:4001".parse().unwrap());
+//! // Second process, player 1
+//! let game = Lockstep::new(MyGame::new(), "127.0.0.1:4001", 1)
+//!     .unwrap()
+//!     .with_peer(0, "127.0.0.1:4000".parse().unwrap());
+//!
+//! engine::create(game, String::from("Lockstep")).start();
+//! ```
+
+use crate::{Engine, Event, Float, Game, UserEvent};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Largest number of inputs sent in one packet, keeps packets well below the datagram limit
+const MAX_INPUTS_PER_PACKET: usize = 64;
+/// Number of recent checksums sent with every packet
+const CHECKSUM_WINDOW: u64 = 32;
+const MAX_PACKET_SIZE: usize = 65_507;
+
+/// Game that can be played in lockstep, see `Lockstep`
+pub trait NetGame: Game {
+    /// Input of one player for one tick, e.g. the pressed buttons
+    type Input: Clone + Default + Serialize + DeserializeOwned;
+
+    /// Samples the local player's input, called once per tick before it's sent to the peers
+    fn local_input(&mut self, engine: &Engine) -> Self::Input;
+
+    /// Inputs of all players for the tick simulated by the next `update`, indexed by player
+    fn set_inputs(&mut self, inputs: &[Self::Input]);
+
+    /// Hash of the game state after `update`. Peers compare them and publish `Desync`
+    /// when they differ.
+    fn checksum(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Published with `Engine::publish` when a peer's checksum differs from the local one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Desync {
+    pub tick: u64,
+    pub player: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Packet<I> {
+    player: usize,
+    /// Tick of the first input in `inputs`
+    start: u64,
+    inputs: Vec<I>,
+    /// Next tick whose input the sender still needs from the receiver
+    ack: u64,
+    checksums: Vec<(u64, u64)>,
+}
+
+struct Peer {
+    player: usize,
+    address: SocketAddr,
+    /// Next tick whose local input the peer still needs
+    ack: u64,
+}
+
+/// Runs a `NetGame` in lockstep with its peers
+pub struct Lockstep<G: NetGame> {
+    game: G,
+    socket: UdpSocket,
+    local_player: usize,
+    peers: Vec<Peer>,
+    input_delay: u64,
+    tick_rate: u32,
+    /// Next tick to simulate
+    tick: u64,
+    /// Next tick to sample the local input for
+    local_tick: u64,
+    /// Received and local inputs by player, then by tick
+    inputs: Vec<BTreeMap<u64, G::Input>>,
+    checksums: BTreeMap<u64, u64>,
+    desync_reported: bool,
+}
+
+impl<G: NetGame> Lockstep<G> {
+    /// Binds the UDP socket at `local_address`. Players are numbered from 0, every number
+    /// other than `local_player` has to be added with `with_peer`.
+    pub fn new(
+        game: G,
+        local_address: impl ToSocketAddrs,
+        local_player: usize,
+    ) -> io::Result<Lockstep<G>> {
+        let socket = UdpSocket::bind(local_address)?;
+        socket.set_nonblocking(true)?;
+
+        let mut lockstep = Lockstep {
+            game,
+            socket,
+            local_player,
+            peers: Vec::new(),
+            input_delay: 3,
+            tick_rate: 60,
+            tick: 0,
+            local_tick: 0,
+            inputs: Vec::new(),
+            checksums: BTreeMap::new(),
+            desync_reported: false,
+        };
+        lockstep.reset_inputs();
+        Ok(lockstep)
+    }
+
+    pub fn with_peer(mut self, player: usize, address: SocketAddr) -> Lockstep<G> {
+        assert!(player != self.local_player, "Player {} is local", player);
+        assert!(
+            self.peers.iter().all(|peer| peer.player != player),
+            "Player {} was already added",
+            player
+        );
+
+        self.peers.push(Peer {
+            player,
+            address,
+            ack: 0,
+        });
+        self.reset_inputs();
+        self
+    }
+
+    /// Number of ticks between sampling an input and simulating it, 3 by default
+    pub fn with_input_delay(mut self, input_delay: u64) -> Lockstep<G> {
+        self.input_delay = input_delay;
+        self.reset_inputs();
+        self
+    }
+
+    /// Ticks per second, each `update` gets `dt` of one tick. 60 by default, which should
+    /// match the engine's target frame rate.
+    pub fn with_tick_rate(mut self, tick_rate: u32) -> Lockstep<G> {
+        self.tick_rate = tick_rate.max(1);
+        self
+    }
+
+    /// Address the socket is bound to, e.g. to find the port picked for port 0
+    pub fn local_address(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Next tick to simulate, i.e. the number of ticks simulated so far
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// One frame of networking: receives inputs, samples the local one with `sample` when
+    /// it's due, runs `simulate` for every tick whose inputs are complete and sends to the
+    /// peers. Returns the first desync found.
+    fn step(
+        &mut self,
+        sample: impl FnOnce(&mut G) -> G::Input,
+        mut simulate: impl FnMut(&mut G, Float),
+    ) -> Option<Desync> {
+        let desync = self.receive();
+
+        if self.local_tick <= self.tick + self.input_delay {
+            let input = sample(&mut self.game);
+            self.inputs[self.local_player].insert(self.local_tick, input);
+            self.local_tick += 1;
+        }
+
+        let dt = 1.0 / self.tick_rate as Float;
+        while let Some(inputs) = self.inputs_for_tick() {
+            self.game.set_inputs(&inputs);
+            simulate(&mut self.game, dt);
+
+            if let Some(checksum) = self.game.checksum() {
+                self.checksums.insert(self.tick, checksum);
+            }
+            self.tick += 1;
+        }
+
+        self.send();
+        self.forget_old_inputs();
+        desync
+    }
+
+    fn reset_inputs(&mut self) {
+        let player_count = self
+            .peers
+            .iter()
+            .map(|peer| peer.player)
+            .fold(self.local_player, usize::max)
+            + 1;
+
+        // Nobody has input for the first ticks, so they run with the default one
+        self.inputs = (0..player_count)
+            .map(|_| {
+                (0..self.input_delay)
+                    .map(|tick| (tick, G::Input::default()))
+                    .collect()
+            })
+            .collect();
+        self.local_tick = self.input_delay;
+        for peer in &mut self.peers {
+            peer.ack = self.input_delay;
+        }
+    }
+
+    fn receive(&mut self) -> Option<Desync> {
+        let mut buffer = vec![0; MAX_PACKET_SIZE];
+        let mut desync = None;
+
+        loop {
+            let size = match self.socket.recv_from(&mut buffer) {
+                Ok((size, _)) => size,
+                // The peer isn't running yet, which some platforms report here
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionRefused
+                    ) =>
+                {
+                    continue
+                }
+                // Nothing left to read, or a socket error that retrying won't fix this frame
+                Err(_) => return desync,
+            };
+            let Ok(packet) = serde_json::from_slice::<Packet<G::Input>>(&buffer[..size]) else {
+                continue;
+            };
+            let Some(peer) = self
+                .peers
+                .iter_mut()
+                .find(|peer| peer.player == packet.player)
+            else {
+                continue;
+            };
+
+            peer.ack = peer.ack.max(packet.ack);
+
+            let inputs = &mut self.inputs[packet.player];
+            for (tick, input) in (packet.start..).zip(packet.inputs) {
+                if tick >= self.tick {
+                    inputs.entry(tick).or_insert(input);
+                }
+            }
+
+            for (tick, checksum) in packet.checksums {
+                let differs = self
+                    .checksums
+                    .get(&tick)
+                    .is_some_and(|local| *local != checksum);
+                if differs && !self.desync_reported {
+                    self.desync_reported = true;
+                    desync = Some(Desync {
+                        tick,
+                        player: packet.player,
+                    });
+                }
+            }
+        }
+    }
+
+    fn send(&self) {
+        let local_inputs = &self.inputs[self.local_player];
+        let checksum_start = self.tick.saturating_sub(CHECKSUM_WINDOW);
+        let checksums: Vec<(u64, u64)> = self
+            .checksums
+            .range(checksum_start..)
+            .map(|(tick, checksum)| (*tick, *checksum))
+            .collect();
+
+        for peer in &self.peers {
+            let packet = Packet {
+                player: self.local_player,
+                start: peer.ack,
+                inputs: local_inputs
+                    .range(peer.ack..)
+                    .take(MAX_INPUTS_PER_PACKET)
+                    .map(|(_, input)| input.clone())
+                    .collect(),
+                ack: self.next_missing_tick(peer.player),
+                checksums: checksums.clone(),
+            };
+
+            // Lost packets are covered by sending everything unacknowledged every frame
+            let _ = self
+                .socket
+                .send_to(&serde_json::to_vec(&packet).unwrap(), peer.address);
+        }
+    }
+
+    fn next_missing_tick(&self, player: usize) -> u64 {
+        let mut tick = self.tick;
+        while self.inputs[player].contains_key(&tick) {
+            tick += 1;
+        }
+        tick
+    }
+
+    fn inputs_for_tick(&self) -> Option<Vec<G::Input>> {
+        self.inputs
+            .iter()
+            .map(|inputs| inputs.get(&self.tick).cloned())
+            .collect()
+    }
+
+    fn forget_old_inputs(&mut self) {
+        for (player, inputs) in self.inputs.iter_mut().enumerate() {
+            // Local inputs are kept until every peer has them
+            let keep_from = if player == self.local_player {
+                self.peers
+                    .iter()
+                    .map(|peer| peer.ack)
+                    .min()
+                    .unwrap_or(self.tick)
+                    .min(self.tick)
+            } else {
+                self.tick
+            };
+            *inputs = inputs.split_off(&keep_from);
+        }
+
+        self.checksums = self
+            .checksums
+            .split_off(&self.tick.saturating_sub(2 * CHECKSUM_WINDOW));
+    }
+}
+
+impl<G: NetGame> Game for Lockstep<G> {
+    fn handle_event(&mut self, event: Event) -> Option<Event> {
+        self.game.handle_event(event)
+    }
+
+    fn update(&mut self, _dt: Float, engine: &Engine) {
+        let desync = self.step(
+            |game| game.local_input(engine),
+            |game, dt| game.update(dt, engine),
+        );
+        if let Some(desync) = desync {
+            engine.publish(desync);
+        }
+    }
+
+    fn render(&mut self, engine: &mut Engine) {
+        self.game.render(engine);
+    }
+
+    fn handle_user_event(&mut self, event: &UserEvent, engine: &mut Engine) {
+        self.game.handle_user_event(event, engine);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Sums the inputs of all players, `offset` makes one peer diverge
+    #[derive(Default)]
+    struct Counter {
+        inputs: Vec<u32>,
+        sum: u64,
+        offset: u64,
+    }
+
+    impl Counter {
+        fn simulate(&mut self) {
+            self.sum += self.inputs.iter().map(|&input| input as u64).sum::<u64>() + self.offset;
+        }
+
+        fn simulate_step(&mut self, _dt: Float) {
+            self.simulate();
+        }
+    }
+
+    impl Game for Counter {
+        fn update(&mut self, _dt: Float, _engine: &Engine) {
+            self.simulate();
+        }
+
+        fn render(&mut self, _engine: &mut Engine) {}
+    }
+
+    impl NetGame for Counter {
+        type Input = u32;
+
+        fn local_input(&mut self, _engine: &Engine) -> u32 {
+            unreachable!("Tests pass the input to `step`")
+        }
+
+        fn set_inputs(&mut self, inputs: &[u32]) {
+            self.inputs = inputs.to_vec();
+        }
+
+        fn checksum(&self) -> Option<u64> {
+            Some(self.sum)
+        }
+    }
+
+    fn connected_pair(first: Counter, second: Counter) -> (Lockstep<Counter>, Lockstep<Counter>) {
+        let first = Lockstep::new(first, "127.0.0.1:0", 0).unwrap();
+        let second = Lockstep::new(second, "127.0.0.1:0", 1).unwrap();
+        let (first_address, second_address) = (
+            first.local_address().unwrap(),
+            second.local_address().unwrap(),
+        );
+        (
+            first.with_peer(1, second_address),
+            second.with_peer(0, first_address),
+        )
+    }
+
+    /// Steps both peers until both simulated `ticks` ticks, returning the desyncs found
+    fn run(
+        first: &mut Lockstep<Counter>,
+        second: &mut Lockstep<Counter>,
+        ticks: u64,
+    ) -> Vec<Desync> {
+        let mut desyncs = Vec::new();
+        for _ in 0..5_000 {
+            if first.tick() >= ticks && second.tick() >= ticks {
+                return desyncs;
+            }
+
+            desyncs.extend(first.step(|_| 1, Counter::simulate_step));
+            desyncs.extend(second.step(|_| 2, Counter::simulate_step));
+            std::thread::sleep(Duration::from_micros(200));
+        }
+        panic!(
+            "Peers stalled at ticks {} and {}",
+            first.tick(),
+            second.tick()
+        );
+    }
+
+    #[test]
+    fn peers_simulate_the_same_ticks() {
+        let (mut first, mut second) = connected_pair(Counter::default(), Counter::default());
+        let desyncs = run(&mut first, &mut second, 30);
+        assert!(desyncs.is_empty());
+
+        // Every tick after the input delay adds both inputs
+        let common_tick = first.tick().min(second.tick()) - 1;
+        assert_eq!(
+            first.checksums.get(&common_tick),
+            second.checksums.get(&common_tick)
+        );
+        assert_eq!(first.checksums[&common_tick], (common_tick + 1 - 3) * 3);
+    }
+
+    #[test]
+    fn diverging_peers_report_a_desync() {
+        let diverging = Counter {
+            offset: 1,
+            ..Counter::default()
+        };
+        let (mut first, mut second) = connected_pair(Counter::default(), diverging);
+
+        let mut desyncs = run(&mut first, &mut second, 30);
+        // Checksums arrive with the packets after the ticks they belong to
+        desyncs.extend(run(&mut first, &mut second, 40));
+
+        // Each side reports the other player once
+        let mut players: Vec<usize> = desyncs.iter().map(|desync| desync.player).collect();
+        players.sort();
+        assert_eq!(players, vec![0, 1]);
+    }
+}
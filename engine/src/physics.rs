@@ -0,0 +1,475 @@
+//! Simple 2D rigid body physics in point space.
+//!
+//! Bodies are circles or axis-aligned boxes without rotation. The world advances in
+//! fixed time steps using semi-implicit Euler integration and resolves contacts with
+//! impulses, so it can be driven straight from the `dt` given to `Game::update`.
+
+use crate::geom::{Circle, Penetration, Rect};
+use crate::{Float, Vec2};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BodyKind {
+    /// Moved by gravity, forces and collisions
+    Dynamic,
+    /// Never moves
+    Static,
+    /// Moves only by its velocity and pushes dynamic bodies out of the way
+    Kinematic,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle { radius: Float },
+    Aabb { half_size: Vec2<Float> },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BodyHandle(usize);
+
+#[derive(Clone, Debug)]
+pub struct Body {
+    pub kind: BodyKind,
+    pub shape: Shape,
+    /// Center of the body
+    pub position: Vec2<Float>,
+    pub velocity: Vec2<Float>,
+    pub mass: Float,
+    /// Bounciness, 0 for no bounce and 1 for a perfectly elastic one
+    pub restitution: Float,
+    pub friction: Float,
+    pub gravity_scale: Float,
+    force: Vec2<Float>,
+}
+
+/// Contact reported after it has been resolved.
+/// `normal` points from `a` towards `b`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Collision {
+    pub a: BodyHandle,
+    pub b: BodyHandle,
+    pub normal: Vec2<Float>,
+    pub depth: Float,
+}
+
+impl Body {
+    pub fn new(kind: BodyKind, shape: Shape, position: impl Into<Vec2<Float>>) -> Body {
+        Body {
+            kind,
+            shape,
+            position: position.into(),
+            velocity: Vec2::ZERO,
+            mass: 1.0,
+            restitution: 0.0,
+            friction: 0.2,
+            gravity_scale: 1.0,
+            force: Vec2::ZERO,
+        }
+    }
+
+    pub fn dynamic(shape: Shape, position: impl Into<Vec2<Float>>) -> Body {
+        Body::new(BodyKind::Dynamic, shape, position)
+    }
+
+    pub fn fixed(shape: Shape, position: impl Into<Vec2<Float>>) -> Body {
+        Body::new(BodyKind::Static, shape, position)
+    }
+
+    pub fn kinematic(shape: Shape, position: impl Into<Vec2<Float>>) -> Body {
+        Body::new(BodyKind::Kinematic, shape, position)
+    }
+
+    pub fn with_velocity(mut self, velocity: Vec2<Float>) -> Body {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: Float) -> Body {
+        self.mass = mass;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: Float) -> Body {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: Float) -> Body {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: Float) -> Body {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+
+    /// Force applied during the next step only
+    pub fn apply_force(&mut self, force: Vec2<Float>) {
+        self.force += force;
+    }
+
+    pub fn apply_impulse(&mut self, impulse: Vec2<Float>) {
+        self.velocity += impulse * self.inverse_mass();
+    }
+
+    pub fn bounds(&self) -> Rect {
+        match self.shape {
+            Shape::Circle { radius } => Circle::new(self.position, radius).bounds(),
+            Shape::Aabb { half_size } => {
+                Rect::from_corners(self.position - half_size, self.position + half_size)
+            }
+        }
+    }
+
+    fn inverse_mass(&self) -> Float {
+        match self.kind {
+            BodyKind::Dynamic if self.mass > 0.0 => 1.0 / self.mass,
+            _ => 0.0,
+        }
+    }
+}
+
+pub struct PhysicsWorld {
+    bodies: Vec<Option<Body>>,
+    pub gravity: Vec2<Float>,
+    time_step: Float,
+    accumulator: Float,
+}
+
+impl PhysicsWorld {
+    /// Maximum steps per `update`, so a long frame doesn't stall the game further
+    const MAX_STEPS: u32 = 8;
+    /// Penetration allowed before positions get corrected, avoids jitter on resting contacts
+    const SLOP: Float = 0.01;
+    /// Fraction of the penetration corrected per step
+    const CORRECTION: Float = 0.8;
+
+    pub fn new(gravity: Vec2<Float>) -> PhysicsWorld {
+        PhysicsWorld {
+            bodies: Vec::new(),
+            gravity,
+            time_step: 1.0 / 120.0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Length of a single simulation step in seconds
+    pub fn with_time_step(mut self, time_step: Float) -> PhysicsWorld {
+        self.time_step = time_step;
+        self
+    }
+
+    pub fn add(&mut self, body: Body) -> BodyHandle {
+        self.bodies.push(Some(body));
+        BodyHandle(self.bodies.len() - 1)
+    }
+
+    pub fn remove(&mut self, handle: BodyHandle) -> Option<Body> {
+        self.bodies.get_mut(handle.0)?.take()
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&Body> {
+        self.bodies.get(handle.0)?.as_ref()
+    }
+
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut Body> {
+        self.bodies.get_mut(handle.0)?.as_mut()
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &Body)> + '_ {
+        self.bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(index, body)| Some((BodyHandle(index), body.as_ref()?)))
+    }
+
+    /// Advances the simulation by `dt` seconds in fixed steps.
+    /// `on_collision` is called for every contact resolved along the way.
+    pub fn update(&mut self, dt: Float, mut on_collision: impl FnMut(Collision)) {
+        self.accumulator += dt;
+
+        let mut steps = 0;
+        while self.accumulator >= self.time_step && steps < Self::MAX_STEPS {
+            self.step(self.time_step, &mut on_collision);
+            self.accumulator -= self.time_step;
+            steps += 1;
+        }
+
+        // Drop the time we couldn't catch up with
+        if steps == Self::MAX_STEPS {
+            self.accumulator = 0.0;
+        }
+    }
+
+    pub fn step(&mut self, dt: Float, mut on_collision: impl FnMut(Collision)) {
+        self.integrate(dt);
+
+        for i in 0..self.bodies.len() {
+            for j in i + 1..self.bodies.len() {
+                if let Some(collision) = self.collide(i, j) {
+                    on_collision(collision);
+                }
+            }
+        }
+    }
+
+    fn integrate(&mut self, dt: Float) {
+        for body in self.bodies.iter_mut().flatten() {
+            match body.kind {
+                BodyKind::Dynamic => {
+                    // Semi-implicit Euler: velocity first, then position with the new velocity
+                    let acceleration =
+                        self.gravity * body.gravity_scale + body.force * body.inverse_mass();
+                    body.velocity += acceleration * dt;
+                    body.position += body.velocity * dt;
+                }
+                BodyKind::Kinematic => body.position += body.velocity * dt,
+                BodyKind::Static => (),
+            }
+            body.force = Vec2::ZERO;
+        }
+    }
+
+    fn collide(&mut self, i: usize, j: usize) -> Option<Collision> {
+        let (head, tail) = self.bodies.split_at_mut(j);
+        let a = head[i].as_mut()?;
+        let b = tail[0].as_mut()?;
+
+        let inverse_mass_sum = a.inverse_mass() + b.inverse_mass();
+        if inverse_mass_sum == 0.0 {
+            return None;
+        }
+
+        let Penetration { normal, depth } = penetration(a, b)?;
+
+        resolve_velocity(a, b, normal);
+
+        // Push the bodies apart proportionally to their inverse mass
+        let correction =
+            normal * ((depth - Self::SLOP).max(0.0) / inverse_mass_sum * Self::CORRECTION);
+        a.position -= correction * a.inverse_mass();
+        b.position += correction * b.inverse_mass();
+
+        Some(Collision {
+            a: BodyHandle(i),
+            b: BodyHandle(j),
+            normal,
+            depth,
+        })
+    }
+}
+
+fn penetration(a: &Body, b: &Body) -> Option<Penetration> {
+    match (a.shape, b.shape) {
+        (
+            Shape::Circle { radius },
+            Shape::Circle {
+                radius: other_radius,
+            },
+        ) => Circle::new(a.position, radius).penetration(&Circle::new(b.position, other_radius)),
+        (Shape::Aabb { .. }, Shape::Aabb { .. }) => a.bounds().penetration(&b.bounds()),
+        (Shape::Circle { radius }, Shape::Aabb { .. }) => {
+            Circle::new(a.position, radius).penetration_rect(&b.bounds())
+        }
+        (Shape::Aabb { .. }, Shape::Circle { radius }) => Circle::new(b.position, radius)
+            .penetration_rect(&a.bounds())
+            .map(|penetration| Penetration {
+                normal: -penetration.normal,
+                depth: penetration.depth,
+            }),
+    }
+}
+
+fn resolve_velocity(a: &mut Body, b: &mut Body, normal: Vec2<Float>) {
+    let (inverse_mass_a, inverse_mass_b) = (a.inverse_mass(), b.inverse_mass());
+    let inverse_mass_sum = inverse_mass_a + inverse_mass_b;
+
+    let relative_velocity = b.velocity - a.velocity;
+    let normal_speed = relative_velocity.dot(normal);
+    if normal_speed > 0.0 {
+        // Already separating
+        return;
+    }
+
+    let restitution = a.restitution.max(b.restitution);
+    let impulse = -(1.0 + restitution) * normal_speed / inverse_mass_sum;
+    a.velocity -= normal * (impulse * inverse_mass_a);
+    b.velocity += normal * (impulse * inverse_mass_b);
+
+    // Coulomb friction along the contact tangent
+    let relative_velocity = b.velocity - a.velocity;
+    let tangent = (relative_velocity - normal * relative_velocity.dot(normal)).normalize();
+    if tangent == Vec2::ZERO {
+        return;
+    }
+
+    let friction = (a.friction * b.friction).sqrt();
+    let friction_impulse = (-relative_velocity.dot(tangent) / inverse_mass_sum)
+        .clamp(-impulse * friction, impulse * friction);
+    a.velocity -= tangent * (friction_impulse * inverse_mass_a);
+    b.velocity += tangent * (friction_impulse * inverse_mass_b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exactly representable, so accumulated steps don't drift
+    const TIME_STEP: Float = 0.125;
+
+    fn world(gravity: Vec2<Float>) -> PhysicsWorld {
+        PhysicsWorld::new(gravity).with_time_step(TIME_STEP)
+    }
+
+    fn ball(position: impl Into<Vec2<Float>>) -> Body {
+        Body::dynamic(Shape::Circle { radius: 1.0 }, position)
+    }
+
+    fn floor() -> Body {
+        Body::fixed(
+            Shape::Aabb {
+                half_size: Vec2::new(1000.0, 10.0),
+            },
+            (0.0, 110.0),
+        )
+    }
+
+    #[test]
+    fn gravity_is_integrated_semi_implicitly() {
+        let mut world = world(Vec2::new(0.0, 10.0));
+        let handle = world.add(ball((0.0, 0.0)));
+
+        for _ in 0..8 {
+            world.update(TIME_STEP, |_| ());
+        }
+
+        // Velocity after each step is used for that step's movement
+        let body = world.body(handle).unwrap();
+        assert_eq!(body.velocity, Vec2::new(0.0, 10.0));
+        assert_eq!(
+            body.position,
+            Vec2::new(0.0, 10.0 * TIME_STEP * TIME_STEP * 36.0)
+        );
+    }
+
+    #[test]
+    fn long_frames_are_clamped_to_max_steps() {
+        let mut world = world(Vec2::new(0.0, 10.0));
+        let handle = world.add(ball((0.0, 0.0)));
+
+        world.update(100.0, |_| ());
+        let steps = PhysicsWorld::MAX_STEPS as Float;
+        assert_eq!(
+            world.body(handle).unwrap().velocity.y,
+            10.0 * TIME_STEP * steps
+        );
+
+        // The rest of the frame is dropped instead of being caught up with later
+        world.update(0.0, |_| ());
+        assert_eq!(
+            world.body(handle).unwrap().velocity.y,
+            10.0 * TIME_STEP * steps
+        );
+    }
+
+    /// Highest point the ball reaches after first touching the floor
+    fn rebound_height(restitution: Float) -> Float {
+        let mut world = PhysicsWorld::new(Vec2::new(0.0, 100.0)).with_time_step(1.0 / 1000.0);
+        world.add(floor());
+        let handle = world.add(ball((0.0, 50.0)).with_restitution(restitution));
+
+        let mut bounced = false;
+        let mut highest: Float = 100.0;
+        for _ in 0..3000 {
+            world.step(1.0 / 1000.0, |_| bounced = true);
+            let body = world.body(handle).unwrap();
+            if bounced {
+                highest = highest.min(body.position.y);
+            }
+            if bounced && body.velocity.y > 0.0 {
+                break;
+            }
+        }
+
+        // The ball rests with its bottom on the floor top at 100
+        99.0 - highest
+    }
+
+    #[test]
+    fn restitution_decides_the_bounce_height() {
+        // Dropped from 49 points above the floor, an elastic bounce reaches r² of that
+        let height = rebound_height(0.5);
+        assert!((height - 49.0 * 0.25).abs() < 1.0, "{}", height);
+
+        assert!(rebound_height(0.0) < 0.5);
+    }
+
+    /// Horizontal speed of a box sliding on the floor for a second
+    fn sliding_speed(friction: Float) -> Float {
+        let mut world = world(Vec2::new(0.0, 100.0));
+        world.add(floor().with_friction(friction));
+        let half_size = Vec2::new(1.0, 1.0);
+        let body = Body::dynamic(Shape::Aabb { half_size }, (0.0, 99.0))
+            .with_velocity(Vec2::new(40.0, 0.0))
+            .with_friction(friction);
+        let handle = world.add(body);
+
+        for _ in 0..8 {
+            world.update(TIME_STEP, |_| ());
+        }
+        world.body(handle).unwrap().velocity.x
+    }
+
+    #[test]
+    fn friction_slows_sliding_bodies() {
+        assert_eq!(sliding_speed(0.0), 40.0);
+
+        let speed = sliding_speed(0.1);
+        assert!(speed > 0.0 && speed < 40.0, "{}", speed);
+        let rougher = sliding_speed(0.3);
+        assert!(rougher < speed, "{} {}", rougher, speed);
+    }
+
+    #[test]
+    fn static_bodies_push_dynamic_ones_out() {
+        let mut world = world(Vec2::ZERO);
+        let floor = world.add(floor());
+        let ball = world.add(ball((0.0, 100.5)).with_velocity(Vec2::new(0.0, 4.0)));
+
+        let mut collisions = Vec::new();
+        world.step(TIME_STEP, |collision| collisions.push(collision));
+
+        assert_eq!(collisions.len(), 1);
+        assert_eq!((collisions[0].a, collisions[0].b), (floor, ball));
+        assert_eq!(collisions[0].normal, Vec2::new(0.0, -1.0));
+
+        let body = world.body(ball).unwrap();
+        assert_eq!(body.velocity, Vec2::ZERO);
+        assert!(body.position.y < 100.5);
+        assert_eq!(world.body(floor).unwrap().position, Vec2::new(0.0, 110.0));
+    }
+
+    #[test]
+    fn kinematic_bodies_push_dynamic_ones_along() {
+        let mut world = world(Vec2::ZERO);
+        let half_size = Vec2::new(1.0, 1.0);
+        let pusher = world.add(
+            Body::kinematic(Shape::Aabb { half_size }, (0.0, 0.0))
+                .with_velocity(Vec2::new(8.0, 0.0)),
+        );
+        let pushed = world.add(Body::dynamic(Shape::Aabb { half_size }, (2.5, 0.0)));
+
+        let mut normals = Vec::new();
+        for _ in 0..8 {
+            world.update(TIME_STEP, |collision| normals.push(collision.normal));
+        }
+
+        assert!(!normals.is_empty());
+        assert!(normals.iter().all(|&normal| normal == Vec2::new(1.0, 0.0)));
+
+        // The pusher keeps its velocity, the pushed body stays ahead of it
+        let pusher = world.body(pusher).unwrap();
+        assert_eq!(pusher.position, Vec2::new(8.0, 0.0));
+        assert!(world.body(pushed).unwrap().position.x > 9.5);
+    }
+}
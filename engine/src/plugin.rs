@@ -0,0 +1,44 @@
+use crate::{Engine, Event, Float, Key};
+
+/// Reusable system hooked into the main loop, registered with `EngineBuilder::with_plugin`.
+///
+/// Every hook runs for all plugins in registration order. A frame runs
+/// `before_event` → `Game::handle_event` → `after_event` for each event, then
+/// `before_update` → `Game::update` → `after_update` unless the engine is paused, and finally
+/// `before_render` → `Game::render` → `after_render`.
+pub trait Plugin {
+    /// Sees each event before the game, returning `None` consumes it
+    fn before_event(&mut self, event: Event, _engine: &mut Engine) -> Option<Event> {
+        Some(event)
+    }
+
+    /// Sees the events the game passed on, returning `None` consumes them
+    fn after_event(&mut self, event: Event, _engine: &mut Engine) -> Option<Event> {
+        Some(event)
+    }
+
+    fn before_update(&mut self, _dt: Float, _engine: &mut Engine) {}
+    fn after_update(&mut self, _dt: Float, _engine: &mut Engine) {}
+
+    fn before_render(&mut self, _engine: &mut Engine) {}
+
+    /// Drawing here ends up on top of the game
+    fn after_render(&mut self, _engine: &mut Engine) {}
+}
+
+/// Quits when the game passes on an Escape key press. Registered by default, see
+/// `EngineBuilder::without_default_plugins`.
+pub struct QuitOnEscape;
+
+impl Plugin for QuitOnEscape {
+    fn after_event(&mut self, event: Event, engine: &mut Engine) -> Option<Event> {
+        if let Event::KeyDown {
+            key: Some(Key::Escape),
+            ..
+        } = event
+        {
+            engine.quit();
+        }
+        Some(event)
+    }
+}
@@ -0,0 +1,540 @@
+//! Named save slots holding serde-serialized game state.
+//!
+//! Every save records the version it was written with. Loading an older save runs the
+//! migrations registered with `Saves::with_migration` one version at a time, so the game
+//! only ever deserializes its current format.
+//!
+//! ```ignore
+//! let saves = Saves::new(save::platform_storage("my_game"))
+//!     .with_version(2)
+//!     // Version 2 added the score
+//!     .with_migration(1, |mut state| {
+//!         state["score"] = 0.into();
+//!         Ok(state)
+//!     });
+//!
+//! saves.save("slot1", &game_state).unwrap();
+//! let game_state: Option<GameState> = saves.load("slot1").unwrap();
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub use serde_json::Value;
+
+/// Where save slots are kept. Slot names are validated by `Saves`, so backends may use
+/// them as file names or keys directly.
+pub trait Storage {
+    fn read(&self, slot: &str) -> io::Result<Option<String>>;
+    fn write(&mut self, slot: &str, data: &str) -> io::Result<()>;
+    /// Deleting a slot that doesn't exist is not an error
+    fn delete(&mut self, slot: &str) -> io::Result<()>;
+    fn slots(&self) -> io::Result<Vec<String>>;
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn read(&self, slot: &str) -> io::Result<Option<String>> {
+        (**self).read(slot)
+    }
+
+    fn write(&mut self, slot: &str, data: &str) -> io::Result<()> {
+        (**self).write(slot, data)
+    }
+
+    fn delete(&mut self, slot: &str) -> io::Result<()> {
+        (**self).delete(slot)
+    }
+
+    fn slots(&self) -> io::Result<Vec<String>> {
+        (**self).slots()
+    }
+}
+
+/// One file per slot in a directory, which is created on the first save
+#[derive(Clone, Debug)]
+pub struct FileStorage {
+    directory: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(directory: impl Into<PathBuf>) -> FileStorage {
+        FileStorage {
+            directory: directory.into(),
+        }
+    }
+
+    /// Directory named `app_name` in the user data directory: `%APPDATA%` on Windows,
+    /// `~/Library/Application Support` on macOS and `$XDG_DATA_HOME` or `~/.local/share`
+    /// elsewhere
+    pub fn in_user_data(app_name: &str) -> FileStorage {
+        let env_path = |name| std::env::var_os(name).map(PathBuf::from);
+
+        let data_directory = if cfg!(windows) {
+            env_path("APPDATA")
+        } else if cfg!(target_os = "macos") {
+            env_path("HOME").map(|home| home.join("Library/Application Support"))
+        } else {
+            env_path("XDG_DATA_HOME")
+                .or_else(|| env_path("HOME").map(|home| home.join(".local/share")))
+        };
+
+        FileStorage::new(data_directory.unwrap_or_default().join(app_name))
+    }
+
+    fn path(&self, slot: &str) -> PathBuf {
+        self.directory.join(format!("{}.json", slot))
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, slot: &str) -> io::Result<Option<String>> {
+        match std::fs::read_to_string(self.path(slot)) {
+            Ok(data) => Ok(Some(data)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn write(&mut self, slot: &str, data: &str) -> io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+
+        // Written next to the slot and renamed, so a crash never leaves half a save behind
+        let temporary_path = self.directory.join(format!("{}.json.tmp", slot));
+        std::fs::write(&temporary_path, data)?;
+        std::fs::rename(temporary_path, self.path(slot))
+    }
+
+    fn delete(&mut self, slot: &str) -> io::Result<()> {
+        match std::fs::remove_file(self.path(slot)) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn slots(&self) -> io::Result<Vec<String>> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        let mut slots = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(slot) = name.to_string_lossy().strip_suffix(".json") {
+                slots.push(slot.to_string());
+            }
+        }
+        slots.sort();
+        Ok(slots)
+    }
+}
+
+/// Slots kept in the browser's `localStorage`, under keys prefixed with the app name
+#[cfg(target_family = "wasm")]
+#[derive(Clone, Debug)]
+pub struct WebStorage {
+    prefix: String,
+}
+
+#[cfg(target_family = "wasm")]
+impl WebStorage {
+    pub fn new(app_name: &str) -> WebStorage {
+        WebStorage {
+            prefix: format!("{}/", app_name),
+        }
+    }
+
+    fn key(&self, slot: &str) -> String {
+        js_string(&format!("{}{}", self.prefix, slot))
+    }
+}
+
+/// Quotes `text` as a JavaScript string literal, JSON strings are valid ones
+#[cfg(target_family = "wasm")]
+fn js_string(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+#[cfg(target_family = "wasm")]
+fn run_script(script: &str) {
+    let script = std::ffi::CString::new(script).unwrap();
+    unsafe { crate::emscripten::emscripten_run_script(script.as_ptr()) };
+}
+
+#[cfg(target_family = "wasm")]
+fn run_script_string(script: &str) -> String {
+    let script = std::ffi::CString::new(script).unwrap();
+    // SAFETY: emscripten returns a NUL-terminated string that stays valid until the next call
+    unsafe {
+        let result = crate::emscripten::emscripten_run_script_string(script.as_ptr());
+        std::ffi::CStr::from_ptr(result)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(target_family = "wasm")]
+impl Storage for WebStorage {
+    fn read(&self, slot: &str) -> io::Result<Option<String>> {
+        // Prefixed with '+' to tell an empty value apart from a missing one
+        let result = run_script_string(&format!(
+            "(function() {{ var value = localStorage.getItem({}); \
+             return value === null ? '' : '+' + value; }})()",
+            self.key(slot)
+        ));
+        Ok(result.strip_prefix('+').map(str::to_string))
+    }
+
+    fn write(&mut self, slot: &str, data: &str) -> io::Result<()> {
+        run_script(&format!(
+            "localStorage.setItem({}, {});",
+            self.key(slot),
+            js_string(data)
+        ));
+        Ok(())
+    }
+
+    fn delete(&mut self, slot: &str) -> io::Result<()> {
+        run_script(&format!("localStorage.removeItem({});", self.key(slot)));
+        Ok(())
+    }
+
+    fn slots(&self) -> io::Result<Vec<String>> {
+        let result = run_script_string(&format!(
+            "(function() {{ var prefix = {}; var slots = []; \
+             for (var i = 0; i < localStorage.length; i++) {{ var key = localStorage.key(i); \
+             if (key.startsWith(prefix)) slots.push(key.substring(prefix.length)); }} \
+             return slots.sort().join('\\n'); }})()",
+            js_string(&self.prefix)
+        ));
+        Ok(result.lines().map(str::to_string).collect())
+    }
+}
+
+/// Keeps slots in memory only, e.g. for tests
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    slots: HashMap<String, String>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, slot: &str) -> io::Result<Option<String>> {
+        Ok(self.slots.get(slot).cloned())
+    }
+
+    fn write(&mut self, slot: &str, data: &str) -> io::Result<()> {
+        self.slots.insert(slot.to_string(), data.to_string());
+        Ok(())
+    }
+
+    fn delete(&mut self, slot: &str) -> io::Result<()> {
+        self.slots.remove(slot);
+        Ok(())
+    }
+
+    fn slots(&self) -> io::Result<Vec<String>> {
+        let mut slots: Vec<String> = self.slots.keys().cloned().collect();
+        slots.sort();
+        Ok(slots)
+    }
+}
+
+/// `FileStorage::in_user_data` on desktop and `WebStorage` on the web
+pub fn platform_storage(app_name: &str) -> Box<dyn Storage> {
+    #[cfg(target_family = "wasm")]
+    return Box::new(WebStorage::new(app_name));
+
+    #[cfg(not(target_family = "wasm"))]
+    return Box::new(FileStorage::in_user_data(app_name));
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// Slot names may only contain ASCII letters, digits, '-' and '_'
+    InvalidSlot(String),
+    /// The save was written by a newer version of the game
+    NewerVersion(u32),
+    Migration {
+        from_version: u32,
+        message: String,
+    },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "Could not access save: {}", error),
+            SaveError::Json(error) => write!(f, "Invalid save: {}", error),
+            SaveError::InvalidSlot(slot) => write!(f, "Invalid save slot name: {:?}", slot),
+            SaveError::NewerVersion(version) => {
+                write!(f, "Save is from the newer version {}", version)
+            }
+            SaveError::Migration {
+                from_version,
+                message,
+            } => write!(
+                f,
+                "Could not migrate save from version {}: {}",
+                from_version, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+#[derive(Serialize, Deserialize)]
+struct SaveData {
+    version: u32,
+    state: Value,
+}
+
+type Migration = Box<dyn Fn(Value) -> Result<Value, String>>;
+
+/// Saves and loads game state in the slots of a `Storage`
+pub struct Saves {
+    storage: Box<dyn Storage>,
+    version: u32,
+    migrations: HashMap<u32, Migration>,
+}
+
+impl Saves {
+    pub fn new(storage: impl Storage + 'static) -> Saves {
+        Saves {
+            storage: Box::new(storage),
+            version: 1,
+            migrations: HashMap::new(),
+        }
+    }
+
+    /// Version written with new saves, 1 by default
+    pub fn with_version(mut self, version: u32) -> Saves {
+        self.version = version;
+        self
+    }
+
+    /// Converts the state of saves written with `from_version` into the format of
+    /// `from_version + 1`
+    pub fn with_migration(
+        mut self,
+        from_version: u32,
+        migrate: impl Fn(Value) -> Result<Value, String> + 'static,
+    ) -> Saves {
+        self.migrations.insert(from_version, Box::new(migrate));
+        self
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn save<T: Serialize>(&mut self, slot: &str, state: &T) -> Result<(), SaveError> {
+        validate_slot(slot)?;
+
+        let data = SaveData {
+            version: self.version,
+            state: serde_json::to_value(state).map_err(SaveError::Json)?,
+        };
+        let data = serde_json::to_string(&data).map_err(SaveError::Json)?;
+        self.storage.write(slot, &data).map_err(SaveError::Io)
+    }
+
+    /// Loads and migrates the state saved in `slot`, `None` if the slot is empty
+    pub fn load<T: DeserializeOwned>(&self, slot: &str) -> Result<Option<T>, SaveError> {
+        validate_slot(slot)?;
+
+        let Some(data) = self.storage.read(slot).map_err(SaveError::Io)? else {
+            return Ok(None);
+        };
+        let SaveData { version, mut state } =
+            serde_json::from_str(&data).map_err(SaveError::Json)?;
+
+        if version > self.version {
+            return Err(SaveError::NewerVersion(version));
+        }
+        for from_version in version..self.version {
+            let migrate =
+                self.migrations
+                    .get(&from_version)
+                    .ok_or_else(|| SaveError::Migration {
+                        from_version,
+                        message: "No migration registered".to_string(),
+                    })?;
+            state = migrate(state).map_err(|message| SaveError::Migration {
+                from_version,
+                message,
+            })?;
+        }
+
+        serde_json::from_value(state)
+            .map(Some)
+            .map_err(SaveError::Json)
+    }
+
+    pub fn delete(&mut self, slot: &str) -> Result<(), SaveError> {
+        validate_slot(slot)?;
+        self.storage.delete(slot).map_err(SaveError::Io)
+    }
+
+    pub fn exists(&self, slot: &str) -> Result<bool, SaveError> {
+        validate_slot(slot)?;
+        Ok(self.storage.read(slot).map_err(SaveError::Io)?.is_some())
+    }
+
+    /// Names of all saved slots, sorted
+    pub fn slots(&self) -> Result<Vec<String>, SaveError> {
+        self.storage.slots().map_err(SaveError::Io)
+    }
+}
+
+fn validate_slot(slot: &str) -> Result<(), SaveError> {
+    let valid = !slot.is_empty()
+        && slot
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(SaveError::InvalidSlot(slot.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct State {
+        name: String,
+        score: u32,
+    }
+
+    fn state() -> State {
+        State {
+            name: String::from("Ada"),
+            score: 12,
+        }
+    }
+
+    /// Storage holding `state` saved with `version` in the slot "old"
+    fn storage_with(version: u32, state: Value) -> MemoryStorage {
+        let mut storage = MemoryStorage::new();
+        let data = json!({ "version": version, "state": state });
+        storage.write("old", &data.to_string()).unwrap();
+        storage
+    }
+
+    #[test]
+    fn saved_state_loads_back() {
+        let mut saves = Saves::new(MemoryStorage::new());
+        assert!(saves.load::<State>("slot").unwrap().is_none());
+        assert!(!saves.exists("slot").unwrap());
+
+        saves.save("slot", &state()).unwrap();
+        assert_eq!(saves.load::<State>("slot").unwrap(), Some(state()));
+        assert!(saves.exists("slot").unwrap());
+        assert_eq!(saves.slots().unwrap(), vec!["slot"]);
+
+        saves.delete("slot").unwrap();
+        assert!(saves.load::<State>("slot").unwrap().is_none());
+    }
+
+    #[test]
+    fn older_saves_are_migrated() {
+        let storage = storage_with(1, json!({ "name": "Ada" }));
+        let saves = Saves::new(storage)
+            .with_version(2)
+            .with_migration(1, |mut state| {
+                state["score"] = 12.into();
+                Ok(state)
+            });
+
+        assert_eq!(saves.load::<State>("old").unwrap(), Some(state()));
+    }
+
+    #[test]
+    fn failing_migrations_are_errors() {
+        let storage = storage_with(1, json!({ "name": "Ada" }));
+        let saves = Saves::new(storage)
+            .with_version(2)
+            .with_migration(1, |_| Err(String::from("broken")));
+
+        let result = saves.load::<State>("old");
+        assert!(matches!(
+            result,
+            Err(SaveError::Migration { from_version: 1, message }) if message == "broken"
+        ));
+    }
+
+    #[test]
+    fn missing_migrations_are_errors() {
+        let storage = storage_with(1, json!({ "name": "Ada" }));
+        let saves = Saves::new(storage)
+            .with_version(3)
+            .with_migration(1, |mut state| {
+                state["score"] = 12.into();
+                Ok(state)
+            });
+
+        let result = saves.load::<State>("old");
+        assert!(matches!(
+            result,
+            Err(SaveError::Migration {
+                from_version: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn newer_saves_are_rejected() {
+        let storage = storage_with(3, serde_json::to_value(state()).unwrap());
+        let saves = Saves::new(storage).with_version(2);
+        assert!(matches!(
+            saves.load::<State>("old"),
+            Err(SaveError::NewerVersion(3))
+        ));
+    }
+
+    #[test]
+    fn slot_names_are_validated() {
+        let mut saves = Saves::new(MemoryStorage::new());
+        for slot in ["", "../escape", "a/b", "slot.json", "two words", "ä"] {
+            assert!(matches!(
+                saves.save(slot, &state()),
+                Err(SaveError::InvalidSlot(_))
+            ));
+            assert!(matches!(
+                saves.load::<State>(slot),
+                Err(SaveError::InvalidSlot(_))
+            ));
+        }
+        saves.save("Slot_1-a", &state()).unwrap();
+    }
+
+    #[test]
+    fn corrupt_saves_are_json_errors() {
+        let mut storage = MemoryStorage::new();
+        storage.write("slot", "{ not json").unwrap();
+        let saves = Saves::new(storage);
+        assert!(matches!(
+            saves.load::<State>("slot"),
+            Err(SaveError::Json(_))
+        ));
+    }
+}
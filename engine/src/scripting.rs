@@ -0,0 +1,496 @@
+//! `Game` driven by a Rhai script (https://rhai.rs), reloaded whenever the file changes.
+//!
+//! The script keeps its state in the map returned by `init()`, which the other functions
+//! reach through `this`. All functions are optional.
+//!
+//! Compile and runtime errors, including scripts running into the operation or call depth
+//! limits, are published as `ScriptError` events, see `Engine::subscribe`. The game keeps
+//! running, so a broken script can be fixed and is picked up on the next change.
+//!
+//! ```rhai
+//! fn init() {
+//!     #{ x: 10.0, y: 10.0, score: 0 }
+//! }
+//!
+//! fn update(dt) {
+//!     if is_key_pressed("Right") { this.x += 40.0 * dt; }
+//! }
+//!
+//! fn render() {
+//!     draw_rect(this.x, this.y, 8, 8, rgb(0, 255, 0));
+//!     draw_text("Score: " + this.score, 2, 2, WHITE);
+//! }
+//!
+//! fn on_event(e) {
+//!     if e.type == "key_down" && e.key == "Space" { this.y -= 10.0; }
+//! }
+//! ```
+//!
+//! Bound functions, all coordinates in points:
+//! `draw_point(x, y, color)`, `draw_rect(x, y, w, h, color)`, `fill_circle(x, y, r, color)`,
+//! `draw_text(text, x, y, color)`, `text_width(text)`, `rgb(r, g, b)`, `rgba(r, g, b, a)`,
+//! `is_key_pressed(name)`, `mouse_x()`, `mouse_y()`, `screen_width()`, `screen_height()`,
+//! `random()` for a float in [0, 1) and `random_int(min, max)` for an int in [min, max).
+//! Color constants such as `WHITE` or `RED` are available as globals.
+//!
+//! Events passed to `on_event` are maps with a `type` of "key_down", "key_up", "mouse_move",
+//! "mouse_down", "mouse_up", "mouse_wheel", "text" or "resize" and the fields of the matching
+//! `Event`.
+
+use crate::{font, geom, Color, Engine, Event, Float, Game, Key, Point, Vec2};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rhai::{CallFnOptions, Dynamic, Map, Module, Scope, AST};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+/// Operations a single call into the script may run, stops endless loops from hanging the game
+const MAX_OPERATIONS: u64 = 10_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+
+/// Error compiling or running the script, published through `Engine::publish`
+#[derive(Clone, Debug)]
+pub struct ScriptError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+enum Command {
+    Point(Point, Color),
+    Rect(Point, u32, u32, Color),
+    Circle(geom::Circle, Color),
+    Text(String, Point, Color),
+}
+
+/// Data shared between the game and the functions bound into the script
+struct Shared {
+    commands: Vec<Command>,
+    pressed_keys: HashSet<Key>,
+    mouse: Point,
+    screen_size: (u32, u32),
+    rng: StdRng,
+}
+
+pub struct ScriptGame {
+    path: PathBuf,
+    rhai: rhai::Engine,
+    ast: Option<AST>,
+    scope: Scope<'static>,
+    state: Dynamic,
+    /// Whether `init` succeeded, it's retried after every reload until it does
+    initialized: bool,
+    shared: Rc<RefCell<Shared>>,
+    modified: Option<SystemTime>,
+    last_error: Option<String>,
+    /// Errors waiting for the next `update` or `render` to publish them
+    errors: Vec<ScriptError>,
+}
+
+impl ScriptGame {
+    /// Loads the script at `path`. Errors are published once the game runs.
+    pub fn new(path: impl Into<PathBuf>) -> ScriptGame {
+        ScriptGame::with_seed(path, rand::random())
+    }
+
+    /// Script game whose `random` and `random_int` are determined by `seed`
+    pub fn with_seed(path: impl Into<PathBuf>, seed: u64) -> ScriptGame {
+        let shared = Rc::new(RefCell::new(Shared {
+            commands: Vec::new(),
+            pressed_keys: HashSet::new(),
+            mouse: Point::ORIGIN,
+            screen_size: (0, 0),
+            rng: StdRng::seed_from_u64(seed),
+        }));
+
+        let mut game = ScriptGame {
+            path: path.into(),
+            rhai: create_rhai_engine(&shared),
+            ast: None,
+            scope: Scope::new(),
+            state: Dynamic::from_map(Map::new()),
+            initialized: false,
+            shared,
+            modified: None,
+            last_error: None,
+            errors: Vec::new(),
+        };
+        game.reload_if_changed();
+        game
+    }
+
+    fn reload_if_changed(&mut self) {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        match self.rhai.compile_file(self.path.clone()) {
+            Ok(ast) => {
+                self.ast = Some(ast);
+                self.last_error = None;
+
+                // State survives reloads, it's only initialized until `init` succeeds once
+                if !self.initialized {
+                    if !self.has_function("init", 0) {
+                        self.initialized = true;
+                    } else if let Some(state) = self.call("init", ()) {
+                        self.state = state;
+                        self.initialized = true;
+                    }
+                }
+            }
+            Err(error) => self.report(error.to_string()),
+        }
+    }
+
+    fn has_function(&self, name: &str, param_count: usize) -> bool {
+        self.ast.as_ref().is_some_and(|ast| {
+            ast.iter_functions()
+                .any(|function| function.name == name && function.params.len() == param_count)
+        })
+    }
+
+    fn call(&mut self, name: &str, args: impl rhai::FuncArgs) -> Option<Dynamic> {
+        let ast = self.ast.as_ref()?;
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+
+        match self
+            .rhai
+            .call_fn_with_options(options, &mut self.scope, ast, name, args)
+        {
+            Ok(result) => Some(result),
+            Err(error) => {
+                self.report(format!("{}: {}", name, error));
+                None
+            }
+        }
+    }
+
+    /// Queues script errors for publishing, each distinct one only once in a row
+    fn report(&mut self, error: String) {
+        if self.last_error.as_ref() != Some(&error) {
+            self.errors.push(ScriptError {
+                path: self.path.clone(),
+                message: error.clone(),
+            });
+            self.last_error = Some(error);
+        }
+    }
+
+    fn publish_errors(&mut self, engine: &Engine) {
+        for error in self.errors.drain(..) {
+            engine.publish(error);
+        }
+    }
+}
+
+impl Game for ScriptGame {
+    fn handle_event(&mut self, event: Event) -> Option<Event> {
+        {
+            let mut shared = self.shared.borrow_mut();
+            match event {
+                Event::KeyDown { key: Some(key), .. } => {
+                    shared.pressed_keys.insert(key);
+                }
+                Event::KeyUp { key: Some(key), .. } => {
+                    shared.pressed_keys.remove(&key);
+                }
+                Event::MouseMotion { x, y, .. } => shared.mouse = Point::new(x, y),
+                _ => {}
+            }
+        }
+
+        if let Some(map) = event_to_map(&event) {
+            if self.has_function("on_event", 1) {
+                self.call("on_event", (map,));
+            }
+        }
+
+        Some(event)
+    }
+
+    fn update(&mut self, dt: Float, engine: &Engine) {
+        #[cfg(not(target_family = "wasm"))]
+        self.reload_if_changed();
+
+        let dimensions = engine.dimensions();
+        self.shared.borrow_mut().screen_size =
+            (dimensions.point_width(), dimensions.point_height());
+
+        if self.has_function("update", 1) {
+            self.call("update", (dt,));
+        }
+        self.publish_errors(engine);
+    }
+
+    fn render(&mut self, engine: &mut Engine) {
+        if self.has_function("render", 0) {
+            self.call("render", ());
+        }
+
+        self.publish_errors(engine);
+
+        let commands = std::mem::take(&mut self.shared.borrow_mut().commands);
+        for command in commands {
+            match command {
+                Command::Point(position, color) => engine.draw_point(position, color),
+                Command::Rect(position, width, height, color) => {
+                    engine.draw_rect(position, width, height, color)
+                }
+                Command::Circle(circle, color) => engine.fill_circle(&circle, color),
+                Command::Text(text, position, color) => engine.draw_text(&text, position, color),
+            }
+        }
+    }
+}
+
+fn create_rhai_engine(shared: &Rc<RefCell<Shared>>) -> rhai::Engine {
+    let mut rhai = rhai::Engine::new();
+    rhai.set_max_operations(MAX_OPERATIONS);
+    rhai.set_max_call_levels(MAX_CALL_LEVELS);
+    rhai.register_type_with_name::<Color>("Color");
+
+    let mut colors = Module::new();
+    for (name, color) in [
+        ("WHITE", Color::WHITE),
+        ("BLACK", Color::BLACK),
+        ("GRAY", Color::GRAY),
+        ("RED", Color::RED),
+        ("GREEN", Color::GREEN),
+        ("BLUE", Color::BLUE),
+        ("MAGENTA", Color::MAGENTA),
+        ("YELLOW", Color::YELLOW),
+        ("CYAN", Color::CYAN),
+    ] {
+        colors.set_var(name, color);
+    }
+    rhai.register_global_module(colors.into());
+
+    rhai.register_fn("rgb", |r: i64, g: i64, b: i64| {
+        Color::rgb(channel(r), channel(g), channel(b))
+    });
+    rhai.register_fn("rgba", |r: i64, g: i64, b: i64, a: i64| {
+        Color::rgba(channel(r), channel(g), channel(b), channel(a))
+    });
+
+    // Coordinates may be ints or floats
+    let point = |x: Dynamic, y: Dynamic| Vec2::new(number(&x), number(&y)).to_point();
+
+    let s = shared.clone();
+    rhai.register_fn("draw_point", move |x: Dynamic, y: Dynamic, color: Color| {
+        s.borrow_mut()
+            .commands
+            .push(Command::Point(point(x, y), color));
+    });
+
+    let s = shared.clone();
+    rhai.register_fn(
+        "draw_rect",
+        move |x: Dynamic, y: Dynamic, width: i64, height: i64, color: Color| {
+            s.borrow_mut().commands.push(Command::Rect(
+                point(x, y),
+                width.max(0) as u32,
+                height.max(0) as u32,
+                color,
+            ));
+        },
+    );
+
+    let s = shared.clone();
+    rhai.register_fn(
+        "fill_circle",
+        move |x: Dynamic, y: Dynamic, radius: Dynamic, color: Color| {
+            let circle = geom::Circle::new(Vec2::new(number(&x), number(&y)), number(&radius));
+            s.borrow_mut().commands.push(Command::Circle(circle, color));
+        },
+    );
+
+    let s = shared.clone();
+    rhai.register_fn(
+        "draw_text",
+        move |text: &str, x: Dynamic, y: Dynamic, color: Color| {
+            s.borrow_mut()
+                .commands
+                .push(Command::Text(text.to_string(), point(x, y), color));
+        },
+    );
+
+    rhai.register_fn("text_width", |text: &str| font::text_size(text).0 as i64);
+
+    let s = shared.clone();
+    rhai.register_fn("is_key_pressed", move |name: &str| {
+        Key::from_name(name).is_some_and(|key| s.borrow().pressed_keys.contains(&key))
+    });
+
+    let s = shared.clone();
+    rhai.register_fn("mouse_x", move || s.borrow().mouse.x as i64);
+    let s = shared.clone();
+    rhai.register_fn("mouse_y", move || s.borrow().mouse.y as i64);
+    let s = shared.clone();
+    rhai.register_fn("screen_width", move || s.borrow().screen_size.0 as i64);
+    let s = shared.clone();
+    rhai.register_fn("screen_height", move || s.borrow().screen_size.1 as i64);
+
+    let s = shared.clone();
+    rhai.register_fn("random", move || s.borrow_mut().rng.gen::<f64>());
+    let s = shared.clone();
+    rhai.register_fn("random_int", move |min: i64, max: i64| {
+        if min >= max {
+            return min;
+        }
+        s.borrow_mut().rng.gen_range(min..max)
+    });
+
+    rhai
+}
+
+fn channel(value: i64) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+fn number(value: &Dynamic) -> Float {
+    value
+        .as_float()
+        .map(|value| value as Float)
+        .or_else(|_| value.as_int().map(|value| value as Float))
+        .unwrap_or(0.0)
+}
+
+fn event_to_map(event: &Event) -> Option<Map> {
+    let mut map = Map::new();
+    let mut set = |name: &str, value: Dynamic| {
+        map.insert(name.into(), value);
+    };
+    let key_name = |key: &Option<Key>| key.map_or(Dynamic::UNIT, |key| format!("{:?}", key).into());
+
+    match event {
+        Event::KeyDown { key, repeat } => {
+            set("type", "key_down".into());
+            set("key", key_name(key));
+            set("repeat", (*repeat).into());
+        }
+        Event::KeyUp { key, repeat } => {
+            set("type", "key_up".into());
+            set("key", key_name(key));
+            set("repeat", (*repeat).into());
+        }
+        Event::MouseMotion { x, y, dx, dy } => {
+            set("type", "mouse_move".into());
+            set("x", (*x as i64).into());
+            set("y", (*y as i64).into());
+            set("dx", (*dx as i64).into());
+            set("dy", (*dy as i64).into());
+        }
+        Event::MouseButtonDown { button, x, y } | Event::MouseButtonUp { button, x, y } => {
+            let kind = if matches!(event, Event::MouseButtonDown { .. }) {
+                "mouse_down"
+            } else {
+                "mouse_up"
+            };
+            set("type", kind.into());
+            set("button", format!("{:?}", button).into());
+            set("x", (*x as i64).into());
+            set("y", (*y as i64).into());
+        }
+        Event::MouseWheel { x, y } => {
+            set("type", "mouse_wheel".into());
+            set("x", (*x as i64).into());
+            set("y", (*y as i64).into());
+        }
+        Event::TextInput { text } => {
+            set("type", "text".into());
+            set("text", text.clone().into());
+        }
+        Event::Resized { width, height } => {
+            set("type", "resize".into());
+            set("width", (*width as i64).into());
+            set("height", (*height as i64).into());
+        }
+        Event::Quit | Event::TextEditing { .. } => return None,
+    }
+
+    Some(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, source: &str) -> ScriptGame {
+        let path = std::env::temp_dir().join(format!("{}-{}.rhai", name, std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        let game = ScriptGame::with_seed(&path, 0);
+        std::fs::remove_file(&path).unwrap();
+        game
+    }
+
+    #[test]
+    fn init_creates_the_state() {
+        let game = load("init", "fn init() { #{ score: 3 } }");
+        assert!(game.errors.is_empty());
+        let state = game.state.cast::<Map>();
+        assert_eq!(state["score"].as_int(), Ok(3));
+    }
+
+    #[test]
+    fn endless_loops_are_stopped_and_reported() {
+        let game = load("endless", "fn init() { loop {} }");
+        assert_eq!(game.errors.len(), 1);
+        assert!(game.errors[0].message.starts_with("init: "));
+    }
+
+    #[test]
+    fn deep_recursion_is_stopped_and_reported() {
+        let game = load(
+            "recursion",
+            "fn down(n) { down(n + 1) } fn init() { down(0) }",
+        );
+        assert_eq!(game.errors.len(), 1);
+    }
+
+    #[test]
+    fn failed_inits_are_retried_after_a_reload() {
+        let path = std::env::temp_dir().join(format!("retry-{}.rhai", std::process::id()));
+        std::fs::write(&path, "fn init() { throw \"not yet\" }").unwrap();
+        let mut game = ScriptGame::with_seed(&path, 0);
+        assert_eq!(game.errors.len(), 1);
+
+        // Forget the modification time, it may not have changed within the file system's
+        // resolution
+        std::fs::write(&path, "fn init() { #{ score: 3 } }").unwrap();
+        game.modified = None;
+        game.reload_if_changed();
+        assert_eq!(game.state.clone().cast::<Map>()["score"].as_int(), Ok(3));
+
+        // Once initialized, reloads keep the state
+        std::fs::write(&path, "fn init() { #{ score: 0 } }").unwrap();
+        game.modified = None;
+        game.reload_if_changed();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(game.state.cast::<Map>()["score"].as_int(), Ok(3));
+    }
+
+    #[test]
+    fn compile_errors_are_reported() {
+        let game = load("broken", "fn init( {");
+        assert_eq!(game.errors.len(), 1);
+        assert!(game.ast.is_none());
+    }
+}
@@ -0,0 +1,39 @@
+/// Handle to an image loaded with `Engine::load_sprite_sheet` or `Engine::create_sprite_sheet`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpriteSheet(pub(crate) usize);
+
+/// Part of a sprite sheet, in sheet pixels
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FrameRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FrameRect {
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> FrameRect {
+        FrameRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Cell `index` of a sheet laid out as a grid with `columns` cells per row
+    pub fn from_grid(index: u32, columns: u32, width: u32, height: u32) -> FrameRect {
+        FrameRect::new(
+            ((index % columns) * width) as i32,
+            ((index / columns) * height) as i32,
+            width,
+            height,
+        )
+    }
+}
+
+impl From<FrameRect> for sdl2::rect::Rect {
+    fn from(frame: FrameRect) -> Self {
+        sdl2::rect::Rect::new(frame.x, frame.y, frame.width, frame.height)
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{Engine, Event, Float, Key, Point};
+
+/// Editable line of text driven by `Event`s, e.g. for name entry or a console.
+///
+/// Editing keys act on both presses and key repeats, so holding Backspace keeps deleting.
+/// The cursor is a byte index that always lies on a character boundary.
+#[derive(Clone, Debug, Default)]
+pub struct TextBuffer {
+    text: String,
+    cursor: usize,
+    composition: String,
+    max_chars: Option<usize>,
+}
+
+impl TextBuffer {
+    pub fn new() -> TextBuffer {
+        TextBuffer::default()
+    }
+
+    pub fn with_text(text: &str) -> TextBuffer {
+        TextBuffer {
+            text: text.to_string(),
+            cursor: text.len(),
+            ..TextBuffer::default()
+        }
+    }
+
+    /// Ignores input that would make the text longer than `max_chars` characters
+    pub fn with_max_chars(mut self, max_chars: usize) -> TextBuffer {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// IME composition in progress, empty when there is none
+    pub fn composition(&self) -> &str {
+        &self.composition
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = text.len();
+        self.composition.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    /// Applies a text or key event, returns true if the event was used
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::TextInput { text } => {
+                self.composition.clear();
+                self.insert(text);
+                true
+            }
+            Event::TextEditing { text, .. } => {
+                self.composition = text.clone();
+                true
+            }
+            // Let the IME handle keys while it composes
+            Event::KeyDown { .. } if !self.composition.is_empty() => true,
+            Event::KeyDown { key: Some(key), .. } => self.edit(*key),
+            _ => false,
+        }
+    }
+
+    /// Inserts `text` at the cursor, truncated to the character limit
+    pub fn insert(&mut self, text: &str) {
+        let available = self.max_chars.map_or(usize::MAX, |max| {
+            max.saturating_sub(self.text.chars().count())
+        });
+        let text: String = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(available)
+            .collect();
+
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    fn edit(&mut self, key: Key) -> bool {
+        match key {
+            Key::Backspace => {
+                if let Some(previous) = self.previous_boundary() {
+                    self.text.replace_range(previous..self.cursor, "");
+                    self.cursor = previous;
+                }
+            }
+            Key::Delete => {
+                if let Some(next) = self.next_boundary() {
+                    self.text.replace_range(self.cursor..next, "");
+                }
+            }
+            Key::Left => self.cursor = self.previous_boundary().unwrap_or(self.cursor),
+            Key::Right => self.cursor = self.next_boundary().unwrap_or(self.cursor),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.text.len(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn previous_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map(|(index, _)| index)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+}
+
+// Text input API
+impl Engine {
+    /// Starts sending `Event::TextInput` and `Event::TextEditing` and shows the on-screen
+    /// keyboard or IME where the platform has one
+    pub fn start_text_input(&mut self) {
+        self.canvas.window().subsystem().text_input().start();
+    }
+
+    pub fn stop_text_input(&mut self) {
+        self.canvas.window().subsystem().text_input().stop();
+    }
+
+    pub fn is_text_input_active(&self) -> bool {
+        self.canvas.window().subsystem().text_input().is_active()
+    }
+
+    /// Area of the text being edited, in points. IMEs place their candidate list next to it.
+    pub fn set_text_input_rect(&mut self, position: impl Into<Point>, width: u32, height: u32) {
+        let rect = crate::dimensions::rect_at(&self.dimensions, position.into(), width, height);
+        // SDL expects window coordinates
+        let ratio = self.dimensions.pixel_ratio();
+        let rect = sdl2::rect::Rect::new(
+            (rect.x() as Float / ratio) as i32,
+            (rect.y() as Float / ratio) as i32,
+            (rect.width() as Float / ratio).ceil() as u32,
+            (rect.height() as Float / ratio).ceil() as u32,
+        );
+        self.canvas.window().subsystem().text_input().set_rect(rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(buffer: &mut TextBuffer, key: Key) -> bool {
+        buffer.handle_event(&Event::KeyDown {
+            key: Some(key),
+            repeat: false,
+        })
+    }
+
+    fn type_text(buffer: &mut TextBuffer, text: &str) {
+        buffer.handle_event(&Event::TextInput {
+            text: text.to_string(),
+        });
+    }
+
+    fn compose(buffer: &mut TextBuffer, text: &str) {
+        buffer.handle_event(&Event::TextEditing {
+            text: text.to_string(),
+            start: 0,
+            length: 0,
+        });
+    }
+
+    #[test]
+    fn edits_respect_multi_byte_characters() {
+        let mut buffer = TextBuffer::with_text("añ€");
+        assert_eq!(buffer.cursor(), 6);
+
+        key(&mut buffer, Key::Backspace);
+        assert_eq!((buffer.text(), buffer.cursor()), ("añ", 3));
+
+        key(&mut buffer, Key::Left);
+        assert_eq!(buffer.cursor(), 1);
+        type_text(&mut buffer, "😀");
+        assert_eq!((buffer.text(), buffer.cursor()), ("a😀ñ", 5));
+
+        key(&mut buffer, Key::Delete);
+        assert_eq!((buffer.text(), buffer.cursor()), ("a😀", 5));
+        key(&mut buffer, Key::Delete);
+        assert_eq!(buffer.text(), "a😀");
+    }
+
+    #[test]
+    fn cursor_stays_inside_the_text() {
+        let mut buffer = TextBuffer::with_text("ño");
+        key(&mut buffer, Key::Right);
+        assert_eq!(buffer.cursor(), 3);
+
+        key(&mut buffer, Key::Home);
+        assert_eq!(buffer.cursor(), 0);
+        key(&mut buffer, Key::Left);
+        key(&mut buffer, Key::Backspace);
+        assert_eq!((buffer.text(), buffer.cursor()), ("ño", 0));
+
+        key(&mut buffer, Key::Right);
+        assert_eq!(buffer.cursor(), 2);
+        key(&mut buffer, Key::End);
+        assert_eq!(buffer.cursor(), 3);
+
+        // Other keys are left to the game
+        assert!(!key(&mut buffer, Key::Space));
+    }
+
+    #[test]
+    fn input_is_cut_to_max_chars() {
+        let mut buffer = TextBuffer::new().with_max_chars(4);
+        type_text(&mut buffer, "äb\ncdef");
+        assert_eq!(buffer.text(), "äbcd");
+
+        type_text(&mut buffer, "g");
+        assert_eq!(buffer.text(), "äbcd");
+
+        key(&mut buffer, Key::Backspace);
+        type_text(&mut buffer, "€€");
+        assert_eq!(buffer.text(), "äbc€");
+    }
+
+    #[test]
+    fn compositions_are_committed_by_text_input() {
+        let mut buffer = TextBuffer::with_text("a");
+        compose(&mut buffer, "にほ");
+        assert_eq!(buffer.composition(), "にほ");
+        assert_eq!(buffer.text(), "a");
+
+        // The IME gets the keys while composing
+        assert!(key(&mut buffer, Key::Backspace));
+        assert_eq!(buffer.text(), "a");
+
+        type_text(&mut buffer, "日本");
+        assert_eq!(buffer.composition(), "");
+        assert_eq!((buffer.text(), buffer.cursor()), ("a日本", 7));
+    }
+
+    #[test]
+    fn cancelled_compositions_leave_the_text_alone() {
+        let mut buffer = TextBuffer::with_text("a");
+        compose(&mut buffer, "に");
+        compose(&mut buffer, "");
+        assert_eq!(buffer.composition(), "");
+
+        key(&mut buffer, Key::Backspace);
+        assert_eq!(buffer.text(), "");
+    }
+}
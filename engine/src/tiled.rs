@@ -0,0 +1,447 @@
+//! Loading of maps made with the Tiled editor (https://www.mapeditor.org).
+//!
+//! Only finite, orthogonal maps are supported and only tile layers and tilesets are read.
+//! Tile data has to be stored as plain arrays or CSV; base64 encoded layers are rejected.
+//!
+//! Maps using a single-image tileset can be drawn with `TileMap::render_sprites`, once the
+//! image is loaded as a sprite sheet:
+//!
+//! ```ignore
+//! // Kept apart from the map, which `tileset_for` borrows while rendering
+//! let mut ground = map.layers.remove(0).tiles;
+//! ground.render_sprites(engine, (0, 0), map.tile_width, sheet, |&id| {
+//!     map.tileset_for(id)?.frame(id)
+//! });
+//! ```
+
+use crate::tilemap::tile_count;
+use crate::{FrameRect, TileMap};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+/// Tiled stores flip and rotation flags in the highest bits of each tile id
+const TILE_ID_MASK: u32 = 0x0FFF_FFFF;
+
+pub struct TiledMap {
+    /// Width in tiles
+    pub width: u32,
+    /// Height in tiles
+    pub height: u32,
+    /// Tile width in pixels, as set in the editor
+    pub tile_width: u32,
+    /// Tile height in pixels, as set in the editor
+    pub tile_height: u32,
+    pub layers: Vec<TiledLayer>,
+    /// Sorted by `first_id`
+    pub tilesets: Vec<TiledTileset>,
+}
+
+pub struct TiledLayer {
+    pub name: String,
+    /// Global tile ids with the flip flags stripped. 0 means an empty tile.
+    pub tiles: TileMap<u32>,
+}
+
+/// Tileset cutting one image into a grid of tiles
+pub struct TiledTileset {
+    /// Global id of the tileset's first tile
+    pub first_id: u32,
+    pub name: String,
+    /// Image path relative to the map file. `None` for external tilesets, which aren't
+    /// read, and for image collections.
+    pub image: Option<String>,
+    /// Tile width in pixels
+    pub tile_width: u32,
+    /// Tile height in pixels
+    pub tile_height: u32,
+    /// Tiles per row of the image
+    pub columns: u32,
+    pub tile_count: u32,
+    /// Pixels around the tiles at the image edges
+    pub margin: u32,
+    /// Pixels between tiles
+    pub spacing: u32,
+}
+
+impl TiledTileset {
+    /// Part of the image showing the global tile `id`, `None` if the tile isn't in this
+    /// tileset or the tileset has no image
+    pub fn frame(&self, id: u32) -> Option<FrameRect> {
+        let index = id.checked_sub(self.first_id)?;
+        if index >= self.tile_count || self.columns == 0 || self.image.is_none() {
+            return None;
+        }
+
+        let x = self.margin + (index % self.columns) * (self.tile_width + self.spacing);
+        let y = self.margin + (index / self.columns) * (self.tile_height + self.spacing);
+        Some(FrameRect::new(
+            x as i32,
+            y as i32,
+            self.tile_width,
+            self.tile_height,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Xml(roxmltree::Error),
+    Unsupported(String),
+    Invalid(String),
+}
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TiledError::Io(error) => write!(f, "Could not read map: {}", error),
+            TiledError::Json(error) => write!(f, "Invalid JSON map: {}", error),
+            TiledError::Xml(error) => write!(f, "Invalid TMX map: {}", error),
+            TiledError::Unsupported(what) => write!(f, "Unsupported map feature: {}", what),
+            TiledError::Invalid(what) => write!(f, "Invalid map: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for TiledError {}
+
+impl TiledMap {
+    pub fn layer(&self, name: &str) -> Option<&TiledLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Tileset containing the global tile `id`, `None` for empty tiles
+    pub fn tileset_for(&self, id: u32) -> Option<&TiledTileset> {
+        if id == 0 {
+            return None;
+        }
+        self.tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.first_id <= id)
+    }
+}
+
+/// Loads a `.json`/`.tmj` or `.tmx` file, picking the format by extension
+pub fn load(path: impl AsRef<Path>) -> Result<TiledMap, TiledError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(TiledError::Io)?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tmx") => from_tmx(&source),
+        _ => from_json(&source),
+    }
+}
+
+pub fn from_json(source: &str) -> Result<TiledMap, TiledError> {
+    #[derive(Deserialize)]
+    struct JsonMap {
+        width: u32,
+        height: u32,
+        tilewidth: u32,
+        tileheight: u32,
+        orientation: String,
+        #[serde(default)]
+        infinite: bool,
+        layers: Vec<JsonLayer>,
+        #[serde(default)]
+        tilesets: Vec<JsonTileset>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonTileset {
+        firstgid: u32,
+        #[serde(default)]
+        name: String,
+        image: Option<String>,
+        #[serde(default)]
+        tilewidth: u32,
+        #[serde(default)]
+        tileheight: u32,
+        #[serde(default)]
+        columns: u32,
+        #[serde(default)]
+        tilecount: u32,
+        #[serde(default)]
+        margin: u32,
+        #[serde(default)]
+        spacing: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonLayer {
+        name: String,
+        #[serde(rename = "type")]
+        kind: String,
+        #[serde(default)]
+        width: u32,
+        #[serde(default)]
+        height: u32,
+        data: Option<JsonData>,
+        encoding: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum JsonData {
+        Tiles(Vec<u32>),
+        Encoded(String),
+    }
+
+    let map: JsonMap = serde_json::from_str(source).map_err(TiledError::Json)?;
+    check_map(&map.orientation, map.infinite)?;
+
+    let mut layers = Vec::new();
+    for layer in map
+        .layers
+        .into_iter()
+        .filter(|layer| layer.kind == "tilelayer")
+    {
+        let tiles = match (layer.data, layer.encoding.as_deref()) {
+            (Some(JsonData::Tiles(tiles)), _) => tiles,
+            (Some(JsonData::Encoded(data)), Some("csv")) => parse_csv(&data)?,
+            (Some(JsonData::Encoded(_)), encoding) => {
+                return Err(unsupported_encoding(encoding));
+            }
+            (None, _) => {
+                return Err(TiledError::Invalid(format!(
+                    "layer {} has no data",
+                    layer.name
+                )))
+            }
+        };
+
+        layers.push(create_layer(layer.name, layer.width, layer.height, tiles)?);
+    }
+
+    let tilesets = map
+        .tilesets
+        .into_iter()
+        .map(|tileset| TiledTileset {
+            first_id: tileset.firstgid,
+            name: tileset.name,
+            image: tileset.image,
+            tile_width: tileset.tilewidth,
+            tile_height: tileset.tileheight,
+            columns: tileset.columns,
+            tile_count: tileset.tilecount,
+            margin: tileset.margin,
+            spacing: tileset.spacing,
+        })
+        .collect();
+
+    Ok(TiledMap {
+        width: map.width,
+        height: map.height,
+        tile_width: map.tilewidth,
+        tile_height: map.tileheight,
+        layers,
+        tilesets: sorted(tilesets),
+    })
+}
+
+pub fn from_tmx(source: &str) -> Result<TiledMap, TiledError> {
+    let document = roxmltree::Document::parse(source).map_err(TiledError::Xml)?;
+    let map = document.root_element();
+    if !map.has_tag_name("map") {
+        return Err(TiledError::Invalid(String::from(
+            "root element is not <map>",
+        )));
+    }
+
+    check_map(
+        map.attribute("orientation").unwrap_or("orthogonal"),
+        map.attribute("infinite") == Some("1"),
+    )?;
+
+    let mut layers = Vec::new();
+    for layer in map.children().filter(|node| node.has_tag_name("layer")) {
+        let name = layer.attribute("name").unwrap_or_default().to_string();
+        let data = layer
+            .children()
+            .find(|node| node.has_tag_name("data"))
+            .ok_or_else(|| TiledError::Invalid(format!("layer {} has no data", name)))?;
+
+        let tiles = match data.attribute("encoding") {
+            Some("csv") => parse_csv(data.text().unwrap_or_default())?,
+            None => data
+                .children()
+                .filter(|node| node.has_tag_name("tile"))
+                .map(|tile| parse_number(tile.attribute("gid").unwrap_or("0")))
+                .collect::<Result<_, _>>()?,
+            encoding => return Err(unsupported_encoding(encoding)),
+        };
+
+        let width = parse_number(layer.attribute("width").unwrap_or("0"))?;
+        let height = parse_number(layer.attribute("height").unwrap_or("0"))?;
+        layers.push(create_layer(name, width, height, tiles)?);
+    }
+
+    let mut tilesets = Vec::new();
+    for tileset in map.children().filter(|node| node.has_tag_name("tileset")) {
+        let number = |name: &str| parse_number(tileset.attribute(name).unwrap_or("0"));
+        let image = tileset
+            .children()
+            .find(|node| node.has_tag_name("image"))
+            .and_then(|image| image.attribute("source"));
+
+        tilesets.push(TiledTileset {
+            first_id: number("firstgid")?,
+            name: tileset.attribute("name").unwrap_or_default().to_string(),
+            image: image.map(String::from),
+            tile_width: number("tilewidth")?,
+            tile_height: number("tileheight")?,
+            columns: number("columns")?,
+            tile_count: number("tilecount")?,
+            margin: number("margin")?,
+            spacing: number("spacing")?,
+        });
+    }
+
+    Ok(TiledMap {
+        width: parse_number(map.attribute("width").unwrap_or("0"))?,
+        height: parse_number(map.attribute("height").unwrap_or("0"))?,
+        tile_width: parse_number(map.attribute("tilewidth").unwrap_or("0"))?,
+        tile_height: parse_number(map.attribute("tileheight").unwrap_or("0"))?,
+        layers,
+        tilesets: sorted(tilesets),
+    })
+}
+
+fn sorted(mut tilesets: Vec<TiledTileset>) -> Vec<TiledTileset> {
+    tilesets.sort_by_key(|tileset| tileset.first_id);
+    tilesets
+}
+
+fn check_map(orientation: &str, infinite: bool) -> Result<(), TiledError> {
+    if orientation != "orthogonal" {
+        return Err(TiledError::Unsupported(format!(
+            "{} orientation",
+            orientation
+        )));
+    }
+    if infinite {
+        return Err(TiledError::Unsupported(String::from("infinite maps")));
+    }
+    Ok(())
+}
+
+fn create_layer(
+    name: String,
+    width: u32,
+    height: u32,
+    tiles: Vec<u32>,
+) -> Result<TiledLayer, TiledError> {
+    if tile_count(width, height) != Some(tiles.len()) {
+        return Err(TiledError::Invalid(format!(
+            "layer {} has {} tiles, expected {}x{}",
+            name,
+            tiles.len(),
+            width,
+            height
+        )));
+    }
+
+    let tiles = tiles.into_iter().map(|id| id & TILE_ID_MASK).collect();
+    Ok(TiledLayer {
+        name,
+        tiles: TileMap::from_vec(width, height, tiles),
+    })
+}
+
+fn parse_csv(data: &str) -> Result<Vec<u32>, TiledError> {
+    data.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(parse_number)
+        .collect()
+}
+
+fn parse_number(value: &str) -> Result<u32, TiledError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| TiledError::Invalid(format!("{} is not a valid number", value)))
+}
+
+fn unsupported_encoding(encoding: Option<&str>) -> TiledError {
+    TiledError::Unsupported(format!("{} layer encoding", encoding.unwrap_or("unknown")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_map(layer_width: u32, layer_height: u32, tiles: &str) -> String {
+        format!(
+            r#"{{"width": 2, "height": 2, "tilewidth": 8, "tileheight": 8,
+                "orientation": "orthogonal", "layers": [{{"name": "ground",
+                "type": "tilelayer", "width": {}, "height": {}, "data": {}}}]}}"#,
+            layer_width, layer_height, tiles
+        )
+    }
+
+    #[test]
+    fn loads_json_layers_without_flip_flags() {
+        let map = from_json(&json_map(2, 2, "[1, 2, 3, 2147483652]")).unwrap();
+        let tiles = &map.layers[0].tiles;
+        assert_eq!((tiles.width(), tiles.height()), (2, 2));
+        assert_eq!(tiles[crate::Point::new(1, 1)], 4);
+    }
+
+    #[test]
+    fn tile_ids_map_to_tileset_frames() {
+        let json = r#"{"width": 1, "height": 1, "tilewidth": 8, "tileheight": 8,
+            "orientation": "orthogonal", "layers": [],
+            "tilesets": [
+                {"firstgid": 7, "source": "external.tsj"},
+                {"firstgid": 1, "name": "ground", "image": "ground.png", "tilewidth": 8,
+                 "tileheight": 8, "columns": 3, "tilecount": 6, "margin": 1, "spacing": 2}
+            ]}"#;
+        let map = from_json(json).unwrap();
+
+        assert!(map.tileset_for(0).is_none());
+        let ground = map.tileset_for(5).unwrap();
+        assert_eq!(ground.name, "ground");
+        assert_eq!(ground.frame(1), Some(FrameRect::new(1, 1, 8, 8)));
+        assert_eq!(ground.frame(5), Some(FrameRect::new(11, 11, 8, 8)));
+        assert_eq!(ground.frame(7), None);
+
+        // External tilesets aren't read, so their tiles have no frames
+        let external = map.tileset_for(7).unwrap();
+        assert_eq!(external.frame(7), None);
+    }
+
+    #[test]
+    fn reads_tmx_tilesets() {
+        let tmx = r#"<map orientation="orthogonal" width="1" height="1" tilewidth="8"
+            tileheight="8"><tileset firstgid="1" name="ground" tilewidth="8" tileheight="8"
+            tilecount="4" columns="2"><image source="ground.png" width="16" height="16"/>
+            </tileset></map>"#;
+        let map = from_tmx(tmx).unwrap();
+
+        let ground = map.tileset_for(4).unwrap();
+        assert_eq!(ground.image.as_deref(), Some("ground.png"));
+        assert_eq!(ground.frame(4), Some(FrameRect::new(8, 8, 8, 8)));
+    }
+
+    #[test]
+    fn rejects_wrong_tile_counts() {
+        let result = from_json(&json_map(2, 2, "[1, 2, 3]"));
+        assert!(matches!(result, Err(TiledError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_sizes_overflowing_the_tile_count() {
+        let result = from_json(&json_map(65_536, 65_536, "[]"));
+        assert!(matches!(result, Err(TiledError::Invalid(_))));
+
+        let tmx = r#"<map orientation="orthogonal" width="2" height="2" tilewidth="8"
+            tileheight="8"><layer name="ground" width="4294967295" height="4294967295">
+            <data encoding="csv"></data></layer></map>"#;
+        let result = from_tmx(tmx);
+        assert!(matches!(result, Err(TiledError::Invalid(_))));
+    }
+}
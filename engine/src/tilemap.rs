@@ -0,0 +1,349 @@
+use crate::{Color, Engine, FrameRect, Layer, LayerCanvas, Point, SpriteSheet};
+use serde::de::Error;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::{Index, IndexMut};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// Unit step in this direction, with north pointing up the screen
+    pub fn offset(self) -> Point {
+        match self {
+            Direction::North => Point::new(0, -1),
+            Direction::East => Point::new(1, 0),
+            Direction::South => Point::new(0, 1),
+            Direction::West => Point::new(-1, 0),
+        }
+    }
+}
+
+/// Fixed-size grid of tiles stored row by row.
+///
+/// Writes through `get_mut`, `set` or `IndexMut` mark the tile as changed,
+/// so `render` only redraws those tiles into its cached layer.
+pub struct TileMap<T> {
+    width: u32,
+    height: u32,
+    tiles: Vec<T>,
+    dirty: Vec<bool>,
+    cache: Option<TileCache>,
+}
+
+/// The layer's pixels depend on the point size too, which changes when `Fit` scaling
+/// follows the browser window
+struct TileCache {
+    layer: Layer,
+    tile_size: u32,
+    point_size: u32,
+}
+
+/// Number of tiles in a `width` x `height` map, `None` if it doesn't fit in memory
+pub(crate) fn tile_count(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize)
+}
+
+impl<T: Clone> TileMap<T> {
+    pub fn new(width: u32, height: u32, tile: T) -> TileMap<T> {
+        let count = tile_count(width, height).expect("Map size overflows");
+        TileMap::from_vec(width, height, vec![tile; count])
+    }
+}
+
+impl<T> TileMap<T> {
+    /// Panics if `tiles` doesn't hold exactly `width * height` elements
+    pub fn from_vec(width: u32, height: u32, tiles: Vec<T>) -> TileMap<T> {
+        assert_eq!(
+            Some(tiles.len()),
+            tile_count(width, height),
+            "Tile count doesn't match the map size"
+        );
+
+        TileMap {
+            width,
+            height,
+            dirty: vec![true; tiles.len()],
+            tiles,
+            cache: None,
+        }
+    }
+
+    /// Width in tiles
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height in tiles
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, position: Point) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.x < self.width as i32
+            && position.y < self.height as i32
+    }
+
+    pub fn index_of(&self, position: Point) -> Option<usize> {
+        if !self.in_bounds(position) {
+            return None;
+        }
+
+        Some(position.y as usize * self.width as usize + position.x as usize)
+    }
+
+    pub fn position_of(&self, index: usize) -> Point {
+        Point::new(
+            (index % self.width as usize) as i32,
+            (index / self.width as usize) as i32,
+        )
+    }
+
+    pub fn get(&self, position: Point) -> Option<&T> {
+        self.index_of(position).map(|index| &self.tiles[index])
+    }
+
+    pub fn get_mut(&mut self, position: Point) -> Option<&mut T> {
+        let index = self.index_of(position)?;
+        self.dirty[index] = true;
+        Some(&mut self.tiles[index])
+    }
+
+    pub fn set(&mut self, position: Point, tile: T) {
+        if let Some(slot) = self.get_mut(position) {
+            *slot = tile;
+        }
+    }
+
+    /// Position of the neighbor in `direction`, if it's inside the map
+    pub fn neighbor(&self, position: Point, direction: Direction) -> Option<Point> {
+        let neighbor = position + direction.offset();
+        self.in_bounds(neighbor).then_some(neighbor)
+    }
+
+    /// In-bounds neighbors in the order north, east, south, west
+    pub fn neighbors(&self, position: Point) -> impl Iterator<Item = (Direction, Point)> + '_ {
+        Direction::ALL.into_iter().filter_map(move |direction| {
+            self.neighbor(position, direction)
+                .map(|neighbor| (direction, neighbor))
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(index, tile)| (self.position_of(index), tile))
+    }
+
+    /// Forces a full redraw on the next `render`
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty.fill(true);
+    }
+
+    /// Draws the map with its top-left corner at `position`, each tile being `tile_size` points wide.
+    ///
+    /// The map is cached in a layer and `draw_tile` is only called for tiles that changed
+    /// since the last call. It receives the tile's top-left corner in layer points and
+    /// the area has already been erased.
+    pub fn render<F>(
+        &mut self,
+        engine: &mut Engine,
+        position: impl Into<Point>,
+        tile_size: u32,
+        mut draw_tile: F,
+    ) where
+        F: FnMut(&mut LayerCanvas, Point, &T),
+    {
+        let layer = self.cache_layer(engine, tile_size);
+
+        if self.dirty.contains(&true) {
+            let tiles = &self.tiles;
+            let dirty = &mut self.dirty;
+            let width = self.width as usize;
+
+            engine.draw_on_layer(layer, |canvas| {
+                for (index, is_dirty) in dirty.iter_mut().enumerate() {
+                    if !*is_dirty {
+                        continue;
+                    }
+
+                    let tile_position = Point::new(
+                        (index % width) as i32 * tile_size as i32,
+                        (index / width) as i32 * tile_size as i32,
+                    );
+
+                    canvas.erase_rect(tile_position, tile_size, tile_size);
+                    draw_tile(canvas, tile_position, &tiles[index]);
+                    *is_dirty = false;
+                }
+            });
+        }
+
+        engine.draw_layer(layer, position);
+    }
+
+    /// `render` for maps where each tile is a single solid color. `None` leaves the tile empty.
+    pub fn render_colored<F>(
+        &mut self,
+        engine: &mut Engine,
+        position: impl Into<Point>,
+        tile_size: u32,
+        color_of: F,
+    ) where
+        F: Fn(&T) -> Option<Color>,
+    {
+        self.render(
+            engine,
+            position,
+            tile_size,
+            |canvas, tile_position, tile| {
+                if let Some(color) = color_of(tile) {
+                    canvas.draw_rect(tile_position, tile_size, tile_size, color);
+                }
+            },
+        );
+    }
+
+    /// `render` for maps drawn from a sprite sheet. `frame_of` picks the part of `sheet`
+    /// showing a tile, every sheet pixel covers one point. `None` leaves the tile empty.
+    pub fn render_sprites<F>(
+        &mut self,
+        engine: &mut Engine,
+        position: impl Into<Point>,
+        tile_size: u32,
+        sheet: SpriteSheet,
+        frame_of: F,
+    ) where
+        F: Fn(&T) -> Option<FrameRect>,
+    {
+        self.render(
+            engine,
+            position,
+            tile_size,
+            |canvas, tile_position, tile| {
+                if let Some(frame) = frame_of(tile) {
+                    canvas.draw_sprite(sheet, frame, tile_position);
+                }
+            },
+        );
+    }
+
+    /// Destroys the cached layer, e.g. before the map is dropped.
+    /// The next `render` creates a new one.
+    pub fn release_cache(&mut self, engine: &mut Engine) {
+        if let Some(cache) = self.cache.take() {
+            engine.destroy_layer(cache.layer);
+        }
+    }
+
+    fn cache_layer(&mut self, engine: &mut Engine, tile_size: u32) -> Layer {
+        let point_size = engine.dimensions().point_size();
+        match &self.cache {
+            Some(cache) if cache.tile_size == tile_size && cache.point_size == point_size => {
+                cache.layer
+            }
+            _ => {
+                if let Some(cache) = self.cache.take() {
+                    engine.destroy_layer(cache.layer);
+                }
+
+                let layer = engine.create_layer(self.width * tile_size, self.height * tile_size);
+                self.cache = Some(TileCache {
+                    layer,
+                    tile_size,
+                    point_size,
+                });
+                self.mark_all_dirty();
+                layer
+            }
+        }
+    }
+}
+
+impl<T> Index<Point> for TileMap<T> {
+    type Output = T;
+
+    fn index(&self, position: Point) -> &T {
+        self.get(position).expect("Tile position out of bounds")
+    }
+}
+
+impl<T> IndexMut<Point> for TileMap<T> {
+    fn index_mut(&mut self, position: Point) -> &mut T {
+        self.get_mut(position).expect("Tile position out of bounds")
+    }
+}
+
+/// Serializes the size and tiles, the render cache is rebuilt after loading
+impl<T: Serialize> Serialize for TileMap<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_struct("TileMap", 3)?;
+        map.serialize_field("width", &self.width)?;
+        map.serialize_field("height", &self.height)?;
+        map.serialize_field("tiles", &self.tiles)?;
+        map.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for TileMap<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Data<T> {
+            width: u32,
+            height: u32,
+            tiles: Vec<T>,
+        }
+
+        let data = Data::deserialize(deserializer)?;
+        if tile_count(data.width, data.height) != Some(data.tiles.len()) {
+            return Err(D::Error::custom("Tile count doesn't match the map size"));
+        }
+        Ok(TileMap::from_vec(data.width, data.height, data.tiles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_valid_maps() {
+        let map: TileMap<u8> =
+            serde_json::from_str(r#"{"width": 2, "height": 1, "tiles": [1, 2]}"#).unwrap();
+        assert_eq!(map[Point::new(1, 0)], 2);
+    }
+
+    #[test]
+    fn rejects_tile_counts_not_matching_the_size() {
+        for data in [
+            r#"{"width": 2, "height": 2, "tiles": [1, 2]}"#,
+            r#"{"width": 4294967295, "height": 4294967295, "tiles": []}"#,
+        ] {
+            assert!(serde_json::from_str::<TileMap<u8>>(data).is_err());
+        }
+    }
+}
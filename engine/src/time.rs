@@ -1,12 +1,234 @@
-pub fn now() -> std::time::Duration {
-    #[cfg(target_family = "wasm")]
-    unsafe {
-        std::time::Duration::from_millis(crate::emscripten::emscripten_get_now() as u64)
-    }
+use crate::Float;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Source of monotonic time. The origin is arbitrary, only differences between readings matter.
+pub trait TimeSource {
+    fn now(&self) -> Duration;
+}
 
+/// Default platform clock.
+/// Uses `Instant` on desktop and `emscripten_get_now` (sub-millisecond) on the web.
+pub struct SystemTimeSource {
     #[cfg(not(target_family = "wasm"))]
-    {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+    start: std::time::Instant,
+}
+
+impl SystemTimeSource {
+    pub fn new() -> SystemTimeSource {
+        SystemTimeSource {
+            #[cfg(not(target_family = "wasm"))]
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        SystemTimeSource::new()
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Duration {
+        #[cfg(target_family = "wasm")]
+        unsafe {
+            Duration::from_secs_f64(crate::emscripten::emscripten_get_now() / 1000.0)
+        }
+
+        #[cfg(not(target_family = "wasm"))]
+        {
+            self.start.elapsed()
+        }
+    }
+}
+
+/// Time source that only moves when told to. Clones share the same time,
+/// so a test can keep one handle and give the other to the engine.
+#[derive(Clone, Default)]
+pub struct ManualTimeSource {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualTimeSource {
+    pub fn new() -> ManualTimeSource {
+        ManualTimeSource::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// Longest frame time passed on as `dt`. Longer gaps, e.g. while the browser tab was in
+/// the background or the game stopped at a breakpoint, would make objects jump.
+pub const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Frame clock driven by the main loop
+pub struct Clock {
+    source: Box<dyn TimeSource>,
+    last: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+    time_scale: Float,
+    paused: bool,
+    step: Option<Duration>,
+}
+
+impl Clock {
+    pub fn new(source: Box<dyn TimeSource>) -> Clock {
+        let last = source.now();
+        Clock {
+            source,
+            last,
+            elapsed: Duration::ZERO,
+            frame_count: 0,
+            time_scale: 1.0,
+            paused: false,
+            step: None,
+        }
+    }
+
+    /// Advances the clock by one frame and returns the scaled delta time in seconds, at
+    /// most `MAX_FRAME_TIME`. Returns `None` while paused unless a step was requested.
+    pub fn tick(&mut self) -> Option<Float> {
+        // Guard against sources that are not strictly monotonic
+        let now = self.source.now().max(self.last);
+        let dt = now - self.last;
+
+        self.last = now;
+        self.elapsed += dt;
+        self.frame_count += 1;
+
+        let dt = if !self.paused {
+            dt.min(MAX_FRAME_TIME)
+        } else {
+            self.step.take()?
+        };
+        self.step = None;
+
+        Some(dt.as_secs_f64() as Float * self.time_scale)
+    }
+
+    /// Real (unscaled) time elapsed since the clock was created, up to the last tick
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Number of ticks so far
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn time_scale(&self) -> Float {
+        self.time_scale
+    }
+
+    /// Multiplier applied to the delta time returned by `tick`
+    pub fn set_time_scale(&mut self, time_scale: Float) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Makes the next `tick` while paused return `frame_time`, scaled like any other frame
+    pub fn step(&mut self, frame_time: Duration) {
+        self.step = Some(frame_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock() -> (Clock, ManualTimeSource) {
+        let source = ManualTimeSource::new();
+        (Clock::new(Box::new(source.clone())), source)
+    }
+
+    #[test]
+    fn tick_returns_time_since_last_tick() {
+        let (mut clock, source) = clock();
+        source.advance(Duration::from_millis(16));
+        assert_eq!(clock.tick(), Some(0.016));
+        source.advance(Duration::from_millis(20));
+        assert_eq!(clock.tick(), Some(0.020));
+        assert_eq!(clock.elapsed(), Duration::from_millis(36));
+        assert_eq!(clock.frame_count(), 2);
+    }
+
+    #[test]
+    fn time_going_backwards_gives_zero() {
+        let (mut clock, source) = clock();
+        source.set(Duration::from_secs(1));
+        clock.tick();
+        source.set(Duration::from_millis(500));
+        assert_eq!(clock.tick(), Some(0.0));
+    }
+
+    #[test]
+    fn time_scale_multiplies_dt() {
+        let (mut clock, source) = clock();
+        clock.set_time_scale(0.5);
+        source.advance(Duration::from_millis(100));
+        assert_eq!(clock.tick(), Some(0.05));
+
+        clock.set_time_scale(-1.0);
+        assert_eq!(clock.time_scale(), 0.0);
+    }
+
+    #[test]
+    fn large_gaps_are_clamped() {
+        let (mut clock, source) = clock();
+        source.advance(Duration::from_secs(10));
+        assert_eq!(clock.tick(), Some(MAX_FRAME_TIME.as_secs_f64()));
+        // Elapsed time stays real
+        assert_eq!(clock.elapsed(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn paused_clock_only_ticks_on_steps() {
+        let (mut clock, source) = clock();
+        clock.set_paused(true);
+        clock.set_time_scale(2.0);
+        source.advance(Duration::from_millis(16));
+        assert_eq!(clock.tick(), None);
+
+        clock.step(Duration::from_millis(10));
+        source.advance(Duration::from_millis(16));
+        assert_eq!(clock.tick(), Some(0.02));
+        source.advance(Duration::from_millis(16));
+        assert_eq!(clock.tick(), None);
+
+        clock.set_paused(false);
+        source.advance(Duration::from_millis(16));
+        assert_eq!(clock.tick(), Some(0.032));
+    }
+
+    #[test]
+    fn steps_while_running_are_dropped() {
+        let (mut clock, source) = clock();
+        clock.step(Duration::from_millis(10));
+        source.advance(Duration::from_millis(16));
+        assert_eq!(clock.tick(), Some(0.016));
+        clock.set_paused(true);
+        assert_eq!(clock.tick(), None);
     }
 }
@@ -0,0 +1,809 @@
+//! Immediate-mode widgets drawn with the engine's own primitives, sized and placed in points.
+//!
+//! Keep a `Ui` in the game, pass it every event from `Game::handle_event` and build the
+//! widgets each frame inside `Game::render`:
+//!
+//! ```ignore
+//! self.ui.frame(engine, Point::new(2, 2), |ui| {
+//!     ui.panel("Settings", |ui| {
+//!         ui.slider("Speed", &mut self.speed, 0.0..=10.0);
+//!         if ui.button("Reset") {
+//!             self.speed = 1.0;
+//!         }
+//!     });
+//! });
+//! ```
+//!
+//! Widgets are identified by their label, text after "##" is not shown and can be used to
+//! tell widgets with the same visible label apart. Clicking a widget or pressing Tab and
+//! Shift+Tab moves the keyboard focus. Return or Space activate the focused button or
+//! checkbox, Left and Right move the focused slider.
+
+use crate::font::{self, GLYPH_HEIGHT};
+use crate::{Color, Engine, Event, Float, Key, MouseButton, Point, TextBuffer};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+
+type Id = u64;
+
+#[derive(Clone, Debug)]
+pub struct Style {
+    pub text: Color,
+    pub panel: Color,
+    pub widget: Color,
+    /// Widget under the mouse
+    pub hot: Color,
+    /// Widget being pressed
+    pub active: Color,
+    /// Check marks, slider fills and panel titles
+    pub accent: Color,
+    /// Outline of the widget with keyboard focus
+    pub focus: Color,
+    /// Space between a widget's border and its content
+    pub padding: u32,
+    /// Space between widgets
+    pub spacing: u32,
+    /// Width of sliders and text fields
+    pub field_width: u32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            text: Color::WHITE,
+            panel: Color::rgba(20, 20, 30, 220),
+            widget: Color::rgb(60, 60, 80),
+            hot: Color::rgb(80, 80, 110),
+            active: Color::rgb(40, 40, 55),
+            accent: Color::rgb(90, 170, 255),
+            focus: Color::YELLOW,
+            padding: 2,
+            spacing: 2,
+            field_width: 96,
+        }
+    }
+}
+
+impl Style {
+    fn widget_height(&self) -> u32 {
+        GLYPH_HEIGHT + 2 * self.padding
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Area {
+    position: Point,
+    width: u32,
+    height: u32,
+}
+
+impl Area {
+    fn contains(&self, point: Point) -> bool {
+        point.x >= self.position.x
+            && point.y >= self.position.y
+            && point.x < self.position.x + self.width as i32
+            && point.y < self.position.y + self.height as i32
+    }
+}
+
+/// Persistent state of the widgets: input, focus and the widget being pressed
+pub struct Ui {
+    pub style: Style,
+    mouse: Point,
+    mouse_down: bool,
+    pressed: bool,
+    released: bool,
+    shift: bool,
+    keys: Vec<(Key, bool)>,
+    text_events: Vec<Event>,
+    active: Option<Id>,
+    focus: Option<Id>,
+    focus_is_text_field: bool,
+    focus_order: Vec<Id>,
+    areas: Vec<Area>,
+    text_input_started: bool,
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Ui::new()
+    }
+}
+
+impl Ui {
+    pub fn new() -> Ui {
+        Ui {
+            style: Style::default(),
+            mouse: Point::new(-1, -1),
+            mouse_down: false,
+            pressed: false,
+            released: false,
+            shift: false,
+            keys: Vec::new(),
+            text_events: Vec::new(),
+            active: None,
+            focus: None,
+            focus_is_text_field: false,
+            focus_order: Vec::new(),
+            areas: Vec::new(),
+            text_input_started: false,
+        }
+    }
+
+    /// Records input for the next frame. Returns true if the UI used the event,
+    /// in which case the game should ignore it.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::MouseMotion { x, y, .. } => {
+                self.mouse = Point::new(x, y);
+                false
+            }
+            Event::MouseButtonDown {
+                button: MouseButton::Left,
+                x,
+                y,
+            } => {
+                self.mouse = Point::new(x, y);
+                self.mouse_down = true;
+                self.pressed = true;
+                self.is_over_ui()
+            }
+            Event::MouseButtonUp {
+                button: MouseButton::Left,
+                x,
+                y,
+            } => {
+                self.mouse = Point::new(x, y);
+                self.mouse_down = false;
+                self.released = true;
+                self.is_over_ui()
+            }
+            Event::KeyDown {
+                key: Some(key),
+                repeat,
+            } => {
+                if matches!(key, Key::LShift | Key::RShift) {
+                    self.shift = true;
+                }
+                // Tab also moves the focus onto the widgets when nothing is focused yet
+                if self.focus.is_none() {
+                    if key != Key::Tab {
+                        return false;
+                    }
+                    self.keys.push((key, repeat));
+                    return !self.focus_order.is_empty();
+                }
+
+                self.keys.push((key, repeat));
+                self.focus_is_text_field
+                    || matches!(
+                        key,
+                        Key::Tab | Key::Return | Key::Space | Key::Left | Key::Right | Key::Escape
+                    )
+            }
+            Event::KeyUp {
+                key: Some(Key::LShift | Key::RShift),
+                ..
+            } => {
+                self.shift = false;
+                false
+            }
+            Event::TextInput { .. } | Event::TextEditing { .. } if self.focus_is_text_field => {
+                self.text_events.push(event.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// True if the mouse is over a widget drawn in the last frame
+    pub fn is_over_ui(&self) -> bool {
+        self.areas.iter().any(|area| area.contains(self.mouse))
+    }
+
+    /// Builds and draws one frame of widgets, laid out vertically starting at `position`
+    pub fn frame(
+        &mut self,
+        engine: &mut Engine,
+        position: impl Into<Point>,
+        build: impl FnOnce(&mut UiFrame),
+    ) {
+        let position = position.into();
+        let mut frame = UiFrame {
+            ui: self,
+            commands: Vec::new(),
+            layouts: vec![Layout::new(position, false)],
+            id_stack: Vec::new(),
+            focus_order: Vec::new(),
+            areas: Vec::new(),
+            text_field_focused: false,
+            text_field_area: None,
+        };
+        build(&mut frame);
+
+        let UiFrame {
+            commands,
+            focus_order,
+            areas,
+            text_field_focused,
+            text_field_area,
+            ..
+        } = frame;
+
+        for command in commands {
+            match command {
+                Command::Rect(area, color) => {
+                    engine.draw_rect(area.position, area.width, area.height, color)
+                }
+                Command::Text(text, position, color) => engine.draw_text(&text, position, color),
+            }
+        }
+
+        self.end_frame(engine, focus_order, areas, text_field_focused);
+        if let Some(area) = text_field_area {
+            engine.set_text_input_rect(area.position, area.width, area.height);
+        }
+    }
+
+    fn end_frame(
+        &mut self,
+        engine: &mut Engine,
+        focus_order: Vec<Id>,
+        areas: Vec<Area>,
+        text_field_focused: bool,
+    ) {
+        let keys = std::mem::take(&mut self.keys);
+        for (key, _) in keys {
+            match key {
+                Key::Tab => {
+                    let current = self
+                        .focus
+                        .and_then(|focus| focus_order.iter().position(|&id| id == focus));
+                    let count = focus_order.len();
+                    self.focus = match (current, self.shift) {
+                        _ if count == 0 => None,
+                        (None, false) => Some(focus_order[0]),
+                        (None, true) => Some(focus_order[count - 1]),
+                        (Some(index), false) => Some(focus_order[(index + 1) % count]),
+                        (Some(index), true) => Some(focus_order[(index + count - 1) % count]),
+                    };
+                }
+                Key::Escape => self.focus = None,
+                _ => {}
+            }
+        }
+
+        // Clicking next to the widgets clears the focus
+        if self.pressed && !areas.iter().any(|area| area.contains(self.mouse)) {
+            self.focus = None;
+        }
+
+        // Focus could have moved to a widget that no longer exists
+        if self
+            .focus
+            .is_some_and(|focus| !focus_order.contains(&focus))
+        {
+            self.focus = None;
+        }
+
+        self.focus_is_text_field = text_field_focused && self.focus.is_some();
+        if self.focus_is_text_field && !self.text_input_started {
+            engine.start_text_input();
+            self.text_input_started = true;
+        } else if !self.focus_is_text_field && self.text_input_started {
+            engine.stop_text_input();
+            self.text_input_started = false;
+        }
+
+        if !self.mouse_down {
+            self.active = None;
+        }
+        self.pressed = false;
+        self.released = false;
+        self.text_events.clear();
+        self.focus_order = focus_order;
+        self.areas = areas;
+    }
+}
+
+enum Command {
+    Rect(Area, Color),
+    Text(String, Point, Color),
+}
+
+struct Layout {
+    origin: Point,
+    cursor: Point,
+    horizontal: bool,
+    width: u32,
+    height: u32,
+}
+
+impl Layout {
+    fn new(origin: Point, horizontal: bool) -> Layout {
+        Layout {
+            origin,
+            cursor: origin,
+            horizontal,
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Interaction {
+    hovered: bool,
+    /// Mouse button held down on the widget
+    held: bool,
+    /// Pressed and released with the mouse, or activated with Return or Space
+    clicked: bool,
+    focused: bool,
+}
+
+/// Widget builder for a single frame, see `Ui::frame`
+pub struct UiFrame<'a> {
+    ui: &'a mut Ui,
+    commands: Vec<Command>,
+    layouts: Vec<Layout>,
+    id_stack: Vec<Id>,
+    focus_order: Vec<Id>,
+    areas: Vec<Area>,
+    text_field_focused: bool,
+    text_field_area: Option<Area>,
+}
+
+impl UiFrame<'_> {
+    pub fn style(&self) -> &Style {
+        &self.ui.style
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let text = visible(text);
+        let (width, height) = font::text_size(text);
+        let position = self.allocate(width, height);
+        self.commands.push(Command::Text(
+            text.to_string(),
+            position,
+            self.ui.style.text,
+        ));
+    }
+
+    /// Returns true when clicked
+    pub fn button(&mut self, label: &str) -> bool {
+        let style = &self.ui.style;
+        let (text_width, _) = font::text_size(visible(label));
+        let width = text_width + 2 * style.padding;
+        let height = style.widget_height();
+
+        let id = self.id(label);
+        let area = self.allocate_area(width, height);
+        let interaction = self.interact(id, area);
+
+        self.draw_widget_background(area, &interaction);
+        self.draw_text_in(label, area);
+        interaction.clicked
+    }
+
+    /// Returns true when the value changed
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let style = &self.ui.style;
+        let box_size = style.widget_height();
+        let (text_width, _) = font::text_size(visible(label));
+        let width = box_size + style.spacing + text_width;
+
+        let id = self.id(label);
+        let area = self.allocate_area(width, box_size);
+        let interaction = self.interact(id, area);
+
+        let box_area = Area {
+            width: box_size,
+            ..area
+        };
+        self.draw_widget_background(box_area, &interaction);
+        if *value {
+            let inset = self.ui.style.padding as i32;
+            self.commands.push(Command::Rect(
+                Area {
+                    position: area.position + Point::new(inset, inset),
+                    width: box_size - 2 * inset as u32,
+                    height: box_size - 2 * inset as u32,
+                },
+                self.ui.style.accent,
+            ));
+        }
+
+        let text_position = area.position
+            + Point::new(
+                (box_size + self.ui.style.spacing) as i32,
+                self.ui.style.padding as i32,
+            );
+        self.commands.push(Command::Text(
+            visible(label).to_string(),
+            text_position,
+            self.ui.style.text,
+        ));
+
+        if interaction.clicked {
+            *value = !*value;
+        }
+        interaction.clicked
+    }
+
+    /// Returns true when the value changed. Left and Right move the value by 1/20 of the range.
+    pub fn slider(&mut self, label: &str, value: &mut Float, range: RangeInclusive<Float>) -> bool {
+        let step = (range.end() - range.start()) / 20.0;
+        let text = format!("{}: {:.2}", visible(label), value);
+        match self.slider_value(label, *value, range, step, text) {
+            Some(new_value) if new_value != *value => {
+                *value = new_value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Slider for whole numbers, Left and Right move the value by 1
+    pub fn slider_u32(&mut self, label: &str, value: &mut u32, range: RangeInclusive<u32>) -> bool {
+        let float_range = *range.start() as Float..=*range.end() as Float;
+        let text = format!("{}: {}", visible(label), value);
+        match self.slider_value(label, *value as Float, float_range, 1.0, text) {
+            Some(new_value) if new_value.round() as u32 != *value => {
+                *value = new_value.round() as u32;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Single line text input. Returns true when Return is pressed while it has focus.
+    pub fn text_field(&mut self, label: &str, buffer: &mut TextBuffer) -> bool {
+        let style = &self.ui.style;
+        let width = style.field_width;
+        let height = style.widget_height();
+        let padding = style.padding as i32;
+
+        let id = self.id(label);
+        let area = self.allocate_area(width, height);
+        let interaction = self.interact(id, area);
+
+        let mut submitted = false;
+        if interaction.focused {
+            self.text_field_focused = true;
+            self.text_field_area = Some(area);
+
+            for event in &self.ui.text_events {
+                buffer.handle_event(event);
+            }
+            for &(key, repeat) in &self.ui.keys {
+                if key == Key::Return {
+                    submitted |= !repeat;
+                } else {
+                    buffer.handle_event(&Event::KeyDown {
+                        key: Some(key),
+                        repeat,
+                    });
+                }
+            }
+        }
+
+        self.draw_widget_background(area, &interaction);
+
+        // Show the end of the text before the cursor when it doesn't fit
+        let visible_chars = ((width as i32 - 2 * padding) / font::ADVANCE as i32).max(1) as usize;
+        let before_cursor = &buffer.text()[..buffer.cursor()];
+        let skip = before_cursor
+            .chars()
+            .count()
+            .saturating_sub(visible_chars - 1);
+        let shown: String = buffer
+            .text()
+            .chars()
+            .skip(skip)
+            .take(visible_chars)
+            .collect();
+        let text_position = area.position + Point::new(padding, padding);
+        self.commands
+            .push(Command::Text(shown, text_position, self.ui.style.text));
+
+        if interaction.focused {
+            let cursor_column = (before_cursor.chars().count() - skip) as i32;
+            let cursor_position =
+                text_position + Point::new(cursor_column * font::ADVANCE as i32 - 1, 0);
+
+            if !buffer.composition().is_empty() {
+                self.commands.push(Command::Text(
+                    buffer.composition().to_string(),
+                    cursor_position + Point::new(1, 0),
+                    self.ui.style.accent,
+                ));
+            }
+            self.commands.push(Command::Rect(
+                Area {
+                    position: cursor_position,
+                    width: 1,
+                    height: GLYPH_HEIGHT,
+                },
+                self.ui.style.focus,
+            ));
+        }
+
+        let label = visible(label);
+        if !label.is_empty() {
+            let label_position =
+                area.position + Point::new((width + self.ui.style.spacing) as i32, padding);
+            self.commands.push(Command::Text(
+                label.to_string(),
+                label_position,
+                self.ui.style.text,
+            ));
+            let (label_width, _) = font::text_size(label);
+            self.extend_last(self.ui.style.spacing + label_width);
+        }
+
+        submitted
+    }
+
+    /// Empty space of `size` points along the layout direction
+    pub fn space(&mut self, size: u32) {
+        if self.layouts.last().unwrap().horizontal {
+            self.allocate(size, 0);
+        } else {
+            self.allocate(0, size);
+        }
+    }
+
+    /// Lays out the widgets added in `build` left to right
+    pub fn horizontal(&mut self, build: impl FnOnce(&mut Self)) {
+        self.nested_layout(true, build);
+    }
+
+    /// Lays out the widgets added in `build` top to bottom
+    pub fn vertical(&mut self, build: impl FnOnce(&mut Self)) {
+        self.nested_layout(false, build);
+    }
+
+    /// Vertical group of widgets on a background, headed by `title`
+    pub fn panel(&mut self, title: &str, build: impl FnOnce(&mut Self)) {
+        let padding = self.ui.style.padding;
+        let position = self.layouts.last().unwrap().cursor;
+
+        // The background has to be drawn first, its size is filled in once the contents are known
+        let background = self.commands.len();
+        self.commands.push(Command::Rect(
+            Area {
+                position,
+                width: 0,
+                height: 0,
+            },
+            self.ui.style.panel,
+        ));
+
+        self.id_stack.push(hash(&self.id_stack, title));
+        self.layouts.push(Layout::new(
+            position + Point::new(padding as i32, padding as i32),
+            false,
+        ));
+
+        let title = visible(title);
+        if !title.is_empty() {
+            let (width, height) = font::text_size(title);
+            let title_position = self.allocate(width, height);
+            self.commands.push(Command::Text(
+                title.to_string(),
+                title_position,
+                self.ui.style.accent,
+            ));
+        }
+        build(self);
+
+        let layout = self.layouts.pop().unwrap();
+        self.id_stack.pop();
+
+        let area = Area {
+            position,
+            width: layout.width + 2 * padding,
+            height: layout.height + 2 * padding,
+        };
+        self.commands[background] = Command::Rect(area, self.ui.style.panel);
+        self.allocate(area.width, area.height);
+        self.areas.push(area);
+    }
+
+    fn nested_layout(&mut self, horizontal: bool, build: impl FnOnce(&mut Self)) {
+        let position = self.layouts.last().unwrap().cursor;
+        self.layouts.push(Layout::new(position, horizontal));
+        build(self);
+
+        let layout = self.layouts.pop().unwrap();
+        self.allocate(layout.width, layout.height);
+    }
+
+    /// Reserves space in the current layout and returns its top-left corner
+    fn allocate(&mut self, width: u32, height: u32) -> Point {
+        let spacing = self.ui.style.spacing as i32;
+        let layout = self.layouts.last_mut().unwrap();
+        let position = layout.cursor;
+
+        layout.width = layout
+            .width
+            .max((position.x - layout.origin.x) as u32 + width);
+        layout.height = layout
+            .height
+            .max((position.y - layout.origin.y) as u32 + height);
+        if layout.horizontal {
+            layout.cursor.x += width as i32 + spacing;
+        } else {
+            layout.cursor.y += height as i32 + spacing;
+        }
+
+        position
+    }
+
+    fn allocate_area(&mut self, width: u32, height: u32) -> Area {
+        let area = Area {
+            position: self.allocate(width, height),
+            width,
+            height,
+        };
+        self.areas.push(area);
+        area
+    }
+
+    /// Widens the last allocation, e.g. for a label drawn next to a widget
+    fn extend_last(&mut self, extra_width: u32) {
+        let spacing = self.ui.style.spacing as i32;
+        let layout = self.layouts.last_mut().unwrap();
+        let area = self.areas.last_mut().unwrap();
+        area.width += extra_width;
+
+        layout.width = layout
+            .width
+            .max((area.position.x - layout.origin.x) as u32 + area.width);
+        if layout.horizontal {
+            layout.cursor.x = area.position.x + area.width as i32 + spacing;
+        }
+    }
+
+    fn id(&self, label: &str) -> Id {
+        hash(&self.id_stack, label)
+    }
+
+    fn interact(&mut self, id: Id, area: Area) -> Interaction {
+        let ui = &mut *self.ui;
+        self.focus_order.push(id);
+
+        let hovered = area.contains(ui.mouse);
+        if hovered && ui.pressed {
+            ui.active = Some(id);
+            ui.focus = Some(id);
+        }
+
+        let focused = ui.focus == Some(id);
+        let activated = focused
+            && ui
+                .keys
+                .iter()
+                .any(|&(key, repeat)| !repeat && matches!(key, Key::Return | Key::Space));
+
+        Interaction {
+            hovered,
+            held: ui.active == Some(id) && ui.mouse_down,
+            clicked: (hovered && ui.released && ui.active == Some(id)) || activated,
+            focused,
+        }
+    }
+
+    fn slider_value(
+        &mut self,
+        label: &str,
+        value: Float,
+        range: RangeInclusive<Float>,
+        step: Float,
+        text: String,
+    ) -> Option<Float> {
+        let style = &self.ui.style;
+        let width = style.field_width;
+        let height = style.widget_height();
+
+        let id = self.id(label);
+        let area = self.allocate_area(width, height);
+        let interaction = self.interact(id, area);
+
+        let (min, max) = (*range.start(), *range.end());
+        let mut new_value = None;
+        if interaction.held {
+            let t = (self.ui.mouse.x - area.position.x) as Float
+                / width.saturating_sub(1).max(1) as Float;
+            new_value = Some(min + (max - min) * t.clamp(0.0, 1.0));
+        } else if interaction.focused {
+            for &(key, _) in &self.ui.keys {
+                let current = new_value.unwrap_or(value);
+                match key {
+                    Key::Left => new_value = Some((current - step).max(min)),
+                    Key::Right => new_value = Some((current + step).min(max)),
+                    _ => {}
+                }
+            }
+        }
+
+        self.draw_widget_background(area, &interaction);
+
+        let t = if max > min {
+            ((new_value.unwrap_or(value) - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let fill_width = (t * width as Float).round() as u32;
+        if fill_width > 0 {
+            self.commands.push(Command::Rect(
+                Area {
+                    width: fill_width,
+                    ..area
+                },
+                self.ui.style.accent.lerp(self.ui.style.widget, 0.5),
+            ));
+        }
+        self.draw_text_in(&text, area);
+
+        new_value
+    }
+
+    fn draw_widget_background(&mut self, area: Area, interaction: &Interaction) {
+        let style = &self.ui.style;
+        let color = if interaction.held {
+            style.active
+        } else if interaction.hovered {
+            style.hot
+        } else {
+            style.widget
+        };
+        self.commands.push(Command::Rect(area, color));
+
+        if interaction.focused {
+            let focus = style.focus;
+            let Area {
+                position,
+                width,
+                height,
+            } = area;
+            for (offset, width, height) in [
+                (Point::new(0, 0), width, 1),
+                (Point::new(0, height as i32 - 1), width, 1),
+                (Point::new(0, 0), 1, height),
+                (Point::new(width as i32 - 1, 0), 1, height),
+            ] {
+                self.commands.push(Command::Rect(
+                    Area {
+                        position: position + offset,
+                        width,
+                        height,
+                    },
+                    focus,
+                ));
+            }
+        }
+    }
+
+    fn draw_text_in(&mut self, text: &str, area: Area) {
+        let padding = self.ui.style.padding as i32;
+        self.commands.push(Command::Text(
+            visible(text).to_string(),
+            area.position + Point::new(padding, padding),
+            self.ui.style.text,
+        ));
+    }
+}
+
+/// Part of a label that is shown, everything before "##"
+fn visible(label: &str) -> &str {
+    label.split("##").next().unwrap()
+}
+
+fn hash(id_stack: &[Id], label: &str) -> Id {
+    let mut hasher = DefaultHasher::new();
+    id_stack.hash(&mut hasher);
+    label.hash(&mut hasher);
+    hasher.finish()
+}
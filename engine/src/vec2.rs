@@ -0,0 +1,171 @@
+use crate::Float;
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Vec2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+/// Integer position on the point grid
+pub type Point = Vec2<i32>;
+
+impl<T> Vec2<T> {
+    pub const fn new(x: T, y: T) -> Vec2<T> {
+        Vec2 { x, y }
+    }
+}
+
+impl<T> Vec2<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    pub fn dot(self, other: Vec2<T>) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the 3D cross product. Positive when `other` is counter-clockwise from `self`.
+    pub fn cross(self, other: Vec2<T>) -> T {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length_squared(self) -> T {
+        self.dot(self)
+    }
+}
+
+impl Vec2<Float> {
+    pub const ZERO: Vec2<Float> = Vec2::new(0.0, 0.0);
+
+    pub fn length(self) -> Float {
+        self.length_squared().sqrt()
+    }
+
+    pub fn distance(self, other: Vec2<Float>) -> Float {
+        (other - self).length()
+    }
+
+    /// Unit vector in the same direction. A zero vector stays zero.
+    pub fn normalize(self) -> Vec2<Float> {
+        let length = self.length();
+        if length == 0.0 {
+            return self;
+        }
+        self / length
+    }
+
+    pub fn lerp(self, other: Vec2<Float>, t: Float) -> Vec2<Float> {
+        self + (other - self) * t
+    }
+
+    /// Rotates by `angle` radians
+    pub fn rotate(self, angle: Float) -> Vec2<Float> {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// Perpendicular vector, rotated 90 degrees counter-clockwise
+    pub fn perpendicular(self) -> Vec2<Float> {
+        Vec2::new(-self.y, self.x)
+    }
+
+    /// Point containing this vector, rounding towards negative infinity
+    pub fn to_point(self) -> Point {
+        Point::new(self.x.floor() as i32, self.y.floor() as i32)
+    }
+}
+
+impl Point {
+    pub const ORIGIN: Point = Point::new(0, 0);
+
+    pub fn to_float(self) -> Vec2<Float> {
+        Vec2::new(self.x as Float, self.y as Float)
+    }
+}
+
+impl From<Point> for Vec2<Float> {
+    fn from(point: Point) -> Self {
+        point.to_float()
+    }
+}
+
+impl From<Vec2<Float>> for Point {
+    fn from(vec: Vec2<Float>) -> Self {
+        vec.to_point()
+    }
+}
+
+impl<T> From<(T, T)> for Vec2<T> {
+    fn from((x, y): (T, T)) -> Self {
+        Vec2::new(x, y)
+    }
+}
+
+impl<T: Add<Output = T>> Add for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn add(self, other: Vec2<T>) -> Vec2<T> {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn sub(self, other: Vec2<T>) -> Vec2<T> {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl<T: Copy + Mul<Output = T>> Mul<T> for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn mul(self, scalar: T) -> Vec2<T> {
+        Vec2::new(self.x * scalar, self.y * scalar)
+    }
+}
+
+impl<T: Copy + Div<Output = T>> Div<T> for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn div(self, scalar: T) -> Vec2<T> {
+        Vec2::new(self.x / scalar, self.y / scalar)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn neg(self) -> Vec2<T> {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+impl<T: AddAssign> AddAssign for Vec2<T> {
+    fn add_assign(&mut self, other: Vec2<T>) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl<T: SubAssign> SubAssign for Vec2<T> {
+    fn sub_assign(&mut self, other: Vec2<T>) {
+        self.x -= other.x;
+        self.y -= other.y;
+    }
+}
+
+impl<T: Copy + MulAssign> MulAssign<T> for Vec2<T> {
+    fn mul_assign(&mut self, scalar: T) {
+        self.x *= scalar;
+        self.y *= scalar;
+    }
+}
+
+impl<T: Copy + DivAssign> DivAssign<T> for Vec2<T> {
+    fn div_assign(&mut self, scalar: T) {
+        self.x /= scalar;
+        self.y /= scalar;
+    }
+}
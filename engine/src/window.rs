@@ -0,0 +1,187 @@
+use crate::config::ScalingMode;
+use crate::save::SaveError;
+use crate::{Color, Engine};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use sdl2::video::WindowPos;
+use serde::{Deserialize, Serialize};
+
+/// Window placement remembered between runs, see `EngineConfig::remember_window`.
+/// The position is in screen coordinates and the size in pixels.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct WindowState {
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl WindowState {
+    /// Slot in the user data directory of the game titled `title`
+    #[cfg(not(target_family = "wasm"))]
+    fn storage(title: &str) -> crate::save::FileStorage {
+        let app_name: String = title
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        crate::save::FileStorage::in_user_data(&app_name)
+    }
+
+    /// Windows are placed by the browser on the web, so nothing is remembered there
+    pub(crate) fn load(title: &str) -> Option<WindowState> {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            use crate::save::Storage;
+            let data = WindowState::storage(title).read("window").ok()??;
+            serde_json::from_str(&data).ok()
+        }
+
+        #[cfg(target_family = "wasm")]
+        {
+            let _ = title;
+            None
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn save(&self, title: &str) -> Result<(), SaveError> {
+        use crate::save::Storage;
+        let data = serde_json::to_string(self).map_err(SaveError::Json)?;
+        WindowState::storage(title)
+            .write("window", &data)
+            .map_err(SaveError::Io)
+    }
+}
+
+// Window API
+impl Engine {
+    pub fn title(&self) -> &str {
+        self.canvas.window().title()
+    }
+
+    /// Fails if `title` contains a nul character
+    pub fn set_title(&mut self, title: &str) -> Result<(), String> {
+        self.canvas
+            .window_mut()
+            .set_title(title)
+            .map_err(|error| error.to_string())
+    }
+
+    /// Sets the window icon from pixels given row by row, like `create_sprite_sheet`.
+    /// The icon isn't scaled by the point size.
+    pub fn set_window_icon(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: &[Color],
+    ) -> Result<(), String> {
+        assert_eq!(
+            pixels.len(),
+            (width * height) as usize,
+            "Pixel count doesn't match the size"
+        );
+
+        let mut bytes: Vec<u8> = pixels
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b, color.a])
+            .collect();
+        let surface = Surface::from_data(
+            &mut bytes,
+            width,
+            height,
+            width * 4,
+            PixelFormatEnum::RGBA32,
+        )?;
+        self.canvas.window_mut().set_icon(surface);
+        Ok(())
+    }
+
+    /// Position of the window's top-left corner on the desktop, in screen coordinates
+    pub fn window_position(&self) -> (i32, i32) {
+        self.canvas.window().position()
+    }
+
+    pub fn set_window_position(&mut self, x: i32, y: i32) {
+        self.canvas
+            .window_mut()
+            .set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    }
+
+    /// Window size in screen coordinates
+    pub fn window_size(&self) -> (u32, u32) {
+        self.canvas.window().size()
+    }
+
+    /// Index of the display showing most of the window
+    pub fn display_index(&self) -> u32 {
+        self.canvas.window().display_index().unwrap_or(0) as u32
+    }
+
+    /// Removes or restores the title bar and frame
+    pub fn set_borderless(&mut self, borderless: bool) {
+        self.canvas.window_mut().set_bordered(!borderless);
+    }
+
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.canvas.window_mut().set_always_on_top(always_on_top);
+    }
+
+    /// How the dimensions follow the browser window on the web, set by the builder's
+    /// `with_*_dimensions` methods or the config
+    pub fn scaling(&self) -> ScalingMode {
+        self.scaling
+    }
+
+    /// Recomputes the dimensions for the browser window's current size and resizes the
+    /// canvas. Returns the `Event::Resized` to pass on if the dimensions changed.
+    #[cfg(target_family = "wasm")]
+    pub(crate) fn resize_to_browser(&mut self) -> Option<crate::Event> {
+        use crate::Float;
+
+        let pixel_ratio = crate::emscripten::device_pixel_ratio();
+        let (width, height) = crate::emscripten::get_window_inner_size();
+        let (dimensions, event) = self.dimensions.resize(
+            self.scaling,
+            (width as Float * pixel_ratio) as u32,
+            (height as Float * pixel_ratio) as u32,
+            pixel_ratio,
+        );
+        event.as_ref()?;
+
+        // SDL sizes the canvas in CSS pixels times the ratio, the element size then makes
+        // it match the points exactly
+        self.canvas
+            .window_mut()
+            .set_size(dimensions.window_width(), dimensions.window_height())
+            .unwrap();
+        crate::emscripten::set_canvas_element_size(
+            dimensions.pixel_width(),
+            dimensions.pixel_height(),
+        );
+
+        let point_size_changed = dimensions.point_size() != self.dimensions.point_size();
+        self.dimensions = dimensions;
+        if point_size_changed {
+            self.rescale_cursor();
+        }
+        event
+    }
+
+    /// Stores the window placement if `EngineConfig::remember_window` is set
+    pub(crate) fn remember_window(&self) -> Result<(), SaveError> {
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(title) = &self.remembered_window_title {
+            let (x, y) = self.window_position();
+            let (width, height) = self.canvas.window().drawable_size();
+            WindowState {
+                x,
+                y,
+                width,
+                height,
+            }
+            .save(title)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::direction;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Cell {
     pub visited: bool,
     pub paths: direction::Type,
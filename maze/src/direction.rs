@@ -1,3 +1,5 @@
+use engine::Direction;
+
 pub type Type = u8;
 
 pub const NORTH: u8 = 0x01;
@@ -5,12 +7,11 @@ pub const EAST: u8 = 0x02;
 pub const SOUTH: u8 = 0x04;
 pub const WEST: u8 = 0x08;
 
-pub fn opposite(direction: Type) -> Type {
+pub fn flag(direction: Direction) -> Type {
     match direction {
-        NORTH => SOUTH,
-        EAST => WEST,
-        SOUTH => NORTH,
-        WEST => EAST,
-        _ => panic!("Invalid direction"),
+        Direction::North => NORTH,
+        Direction::East => EAST,
+        Direction::South => SOUTH,
+        Direction::West => WEST,
     }
 }
@@ -2,118 +2,305 @@ mod cell;
 mod direction;
 
 use cell::Cell;
-use rand::random;
-use sdl2::pixels::Color;
+use rand::{random, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 
-use engine::{Engine, Float, Game, Point};
+use engine::config::EngineConfig;
+use engine::save::{self, Saves};
+use engine::ui::Ui;
+use engine::{Color, Direction, Engine, Event, Float, Game, Key, LayerCanvas, Point, TileMap};
 
+/// Slot that F5 saves the maze to and F9 loads it from
+const SAVE_SLOT: &str = "maze";
+
+/// `[game]` table of maze.toml, values can be overridden with e.g. `--set columns=60`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct MazeSettings {
+    columns: u32,
+    rows: u32,
+    path_width: u32,
+    /// Cells carved per second of game time
+    carve_rate: Float,
+}
+
+impl Default for MazeSettings {
+    fn default() -> Self {
+        MazeSettings {
+            columns: 40,
+            rows: 20,
+            path_width: 3,
+            carve_rate: 60.0,
+        }
+    }
+}
+
+impl MazeSettings {
+    /// Rejects settings that can't make a maze, e.g. from `--set rows=0`
+    fn validate(&self) -> Result<(), String> {
+        if self.columns == 0 || self.rows == 0 {
+            return Err(format!(
+                "The maze needs at least one column and row, got {}x{}",
+                self.columns, self.rows
+            ));
+        }
+        if self.path_width == 0 {
+            return Err(String::from("path_width must be at least 1"));
+        }
+        if !(self.carve_rate > 0.0 && self.carve_rate.is_finite()) {
+            return Err(format!(
+                "carve_rate must be positive, got {}",
+                self.carve_rate
+            ));
+        }
+
+        // The window size in points has to fit in a u32
+        let cell_size = self.path_width.checked_add(1);
+        let fits = |cells: u32| cell_size?.checked_mul(cells)?.checked_add(1);
+        if fits(self.columns).is_none() || fits(self.rows).is_none() {
+            return Err(format!(
+                "The maze {}x{} is too large",
+                self.columns, self.rows
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Published once every cell has been visited
+struct MazeCompleted {
+    cells: u32,
+    game_time: Float,
+}
+
+/// Saved with the carving state, so a half-finished maze resumes where it left off
+#[derive(Serialize, Deserialize)]
 struct Maze {
-    width: u32,
-    height: u32,
-    path_width: i32,
+    path_width: u32,
+    carve_rate: Float,
+    carve_progress: Float,
+    game_time: Float,
+    completed: bool,
     num_visited_cells: u32,
     cells_to_visit: Vec<Point>,
-    cells: Vec<Cell>,
+    cells: TileMap<Cell>,
+    /// Same generator as `StdRng`, which can't be serialized
+    rng: ChaCha12Rng,
+    /// Edited in the settings panel, applied on regenerate
+    settings: MazeSettings,
+    #[serde(skip)]
+    show_settings: bool,
+    #[serde(skip)]
+    ui: Ui,
+    #[serde(skip)]
+    load_requested: bool,
+    /// Window title from the config, the carving progress is shown after it
+    #[serde(skip)]
+    title: String,
+    /// Carving progress in percent last shown in the window title
+    #[serde(skip)]
+    title_progress: Option<u32>,
 }
 
 impl Maze {
-    fn new(width: u32, height: u32, path_width: u32) -> Maze {
-        Maze {
-            width,
-            height,
+    fn new(settings: MazeSettings, seed: u64, title: String) -> Maze {
+        let mut maze = Maze {
             num_visited_cells: 0,
-            path_width: path_width as i32,
+            path_width: settings.path_width,
+            carve_rate: settings.carve_rate,
+            carve_progress: 0.0,
+            game_time: 0.0,
+            completed: false,
             cells_to_visit: Vec::new(),
-            cells: vec![Cell::new(); (width * height) as usize],
-        }
+            cells: TileMap::new(settings.columns, settings.rows, Cell::new()),
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            settings,
+            show_settings: false,
+            ui: Ui::new(),
+            load_requested: false,
+            title,
+            title_progress: None,
+        };
+        maze.restart();
+        maze
     }
 
-    fn get_cell_xy(&mut self, x: i32, y: i32) -> Option<&mut Cell> {
-        if x >= self.width as i32 || y >= self.height as i32 || x < 0 || y < 0 {
-            return None;
-        }
+    /// Starts carving a new maze with the current settings
+    fn restart(&mut self) {
+        let settings = &self.settings;
+        self.path_width = settings.path_width;
+        self.carve_rate = settings.carve_rate;
+        self.cells = TileMap::new(settings.columns, settings.rows, Cell::new());
 
-        Some(self.get_cell(Point::new(x, y)))
+        self.cells[Point::ORIGIN].visited = true;
+        self.cells_to_visit = vec![Point::ORIGIN];
+        self.num_visited_cells = 1;
+        self.carve_progress = 0.0;
+        self.game_time = 0.0;
+        self.completed = false;
     }
 
-    fn get_cell(&mut self, position: Point) -> &mut Cell {
-        &mut self.cells[(position.y * (self.width as i32) + position.x) as usize]
-    }
+    /// Checks what carving relies on, so a corrupt save is reported instead of panicking later
+    fn validate(&self) -> Result<(), String> {
+        self.settings.validate()?;
+        if self.path_width == 0 {
+            return Err(String::from("path_width must be at least 1"));
+        }
+        if !(self.carve_rate > 0.0 && self.carve_rate.is_finite()) {
+            return Err(format!(
+                "carve_rate must be positive, got {}",
+                self.carve_rate
+            ));
+        }
+
+        let cells = self.cells.width().checked_mul(self.cells.height());
+        let Some(cells) = cells.filter(|&cells| cells > 0) else {
+            return Err(format!(
+                "The maze {}x{} has no cells or too many",
+                self.cells.width(),
+                self.cells.height()
+            ));
+        };
 
-    fn get_neighbor_cell_position(&mut self, position: Point, direction: direction::Type) -> Point {
-        let Point { x, y } = position;
-        match direction {
-            direction::NORTH => Point::new(x, y - 1),
-            direction::EAST => Point::new(x + 1, y),
-            direction::SOUTH => Point::new(x, y + 1),
-            direction::WEST => Point::new(x - 1, y),
-            _ => panic!("Invalid direction"),
+        let visited = self.cells.iter().filter(|(_, cell)| cell.visited).count();
+        if visited != self.num_visited_cells as usize {
+            return Err(format!(
+                "{} cells are visited, but {} are counted",
+                visited, self.num_visited_cells
+            ));
         }
+
+        if let Some(&position) = self
+            .cells_to_visit
+            .iter()
+            .find(|&&position| !self.cells.get(position).is_some_and(|cell| cell.visited))
+        {
+            return Err(format!(
+                "Cell {:?} to visit is outside the maze or unvisited",
+                position
+            ));
+        }
+        if self.num_visited_cells < cells && self.cells_to_visit.is_empty() {
+            return Err(String::from(
+                "The maze is unfinished but has no cells to visit",
+            ));
+        }
+
+        Ok(())
     }
 
-    fn get_neighbor_cell(
-        &mut self,
-        position: Point,
-        direction: direction::Type,
-    ) -> Option<&mut Cell> {
-        let neighbor_position = self.get_neighbor_cell_position(position, direction);
-        self.get_cell_xy(neighbor_position.x, neighbor_position.y)
+    fn saves() -> Saves {
+        Saves::new(save::platform_storage("maze"))
     }
 
-    fn draw_cell(&self, x: i32, y: i32, cell: Cell, engine: &mut Engine) {
-        const CELL_BORDER_OFFSET: i32 = 1;
-
-        // Draw cell
-        for py in 0..self.path_width {
-            for px in 0..self.path_width {
-                if cell.visited {
-                    engine.draw_point(
-                        x * (self.path_width + 1) + px + CELL_BORDER_OFFSET,
-                        y * (self.path_width + 1) + py + CELL_BORDER_OFFSET,
-                        Color::WHITE,
-                    )
-                } else {
-                    engine.draw_point(
-                        x * (self.path_width + 1) + px + CELL_BORDER_OFFSET,
-                        y * (self.path_width + 1) + py + CELL_BORDER_OFFSET,
-                        Color::BLUE,
-                    )
+    fn save(&self) {
+        match Maze::saves().save(SAVE_SLOT, self) {
+            Ok(()) => println!("Saved the maze"),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+
+    /// Replaces this maze with the saved one, if there is one
+    fn load(&mut self, engine: &mut Engine) {
+        match Maze::saves().load::<Maze>(SAVE_SLOT) {
+            Ok(Some(saved)) => {
+                if let Err(error) = saved.validate() {
+                    eprintln!("The saved maze is invalid: {}", error);
+                    return;
                 }
+
+                self.cells.release_cache(engine);
+                *self = Maze {
+                    show_settings: self.show_settings,
+                    title: std::mem::take(&mut self.title),
+                    ..saved
+                };
+                println!("Loaded the saved maze");
             }
+            Ok(None) => println!("There is no saved maze"),
+            Err(error) => eprintln!("{}", error),
         }
+    }
 
-        // Draw paths
-        for p in 0..self.path_width {
-            if cell.paths & direction::SOUTH != 0 {
-                engine.draw_point(
-                    x * (self.path_width + 1) + p + CELL_BORDER_OFFSET,
-                    y * (self.path_width + 1) + self.path_width + CELL_BORDER_OFFSET,
-                    Color::WHITE,
-                )
-            }
+    fn render_settings(&mut self, engine: &mut Engine) {
+        let dimensions = engine.dimensions();
+        let (point_width, point_height) = (dimensions.point_width(), dimensions.point_height());
 
-            if cell.paths & direction::EAST != 0 {
-                engine.draw_point(
-                    x * (self.path_width + 1) + self.path_width + CELL_BORDER_OFFSET,
-                    y * (self.path_width + 1) + p + CELL_BORDER_OFFSET,
-                    Color::WHITE,
-                )
-            }
+        let settings = &mut self.settings;
+        let mut regenerate = false;
+        self.ui.frame(engine, Point::new(2, 2), |ui| {
+            ui.panel("Maze settings (F1)", |ui| {
+                ui.slider_u32("Path width", &mut settings.path_width, 1..=8);
+
+                // Keep the maze inside the window
+                let cell_size = settings.path_width + 1;
+                let max_columns = ((point_width - 1) / cell_size).max(2);
+                let max_rows = ((point_height - 1) / cell_size).max(2);
+                settings.columns = settings.columns.min(max_columns);
+                settings.rows = settings.rows.min(max_rows);
+
+                ui.slider_u32("Columns", &mut settings.columns, 2..=max_columns);
+                ui.slider_u32("Rows", &mut settings.rows, 2..=max_rows);
+                ui.slider("Speed", &mut settings.carve_rate, 1.0..=600.0);
+                regenerate = ui.button("Regenerate");
+            });
+        });
+
+        // Speed applies right away, everything else on regenerate
+        self.carve_rate = self.settings.carve_rate;
+        if regenerate {
+            self.cells.release_cache(engine);
+            self.restart();
         }
     }
 }
 
-impl Game for Maze {
-    fn update(&mut self, _dt: Float, _engine: &Engine) {
-        if self.num_visited_cells >= (self.width * self.height) {
+fn draw_cell(canvas: &mut LayerCanvas, position: Point, cell: &Cell, path_width: u32) {
+    // Draw cell
+    let cell_color = if cell.visited {
+        Color::WHITE
+    } else {
+        Color::BLUE
+    };
+    canvas.draw_rect(position, path_width, path_width, cell_color);
+
+    // Draw paths
+    if cell.paths & direction::SOUTH != 0 {
+        canvas.draw_rect(
+            position + Point::new(0, path_width as i32),
+            path_width,
+            1,
+            Color::WHITE,
+        );
+    }
+
+    if cell.paths & direction::EAST != 0 {
+        canvas.draw_rect(
+            position + Point::new(path_width as i32, 0),
+            1,
+            path_width,
+            Color::WHITE,
+        );
+    }
+}
+
+impl Maze {
+    fn carve_next_cell(&mut self) {
+        if self.num_visited_cells >= (self.cells.width() * self.cells.height()) {
             return;
         }
 
         let mut current_cell: Point;
-        let mut neighbors: Vec<direction::Type>;
+        let mut neighbors: Vec<Direction>;
 
         loop {
-            current_cell = *self.cells_to_visit.last().unwrap();
+            // Only empty if every reachable cell has been visited
+            let Some(&cell) = self.cells_to_visit.last() else {
+                return;
+            };
+            current_cell = cell;
 
             neighbors = get_unvisited_neighbours(self, current_cell);
             if neighbors.is_empty() {
@@ -126,16 +313,19 @@ impl Game for Maze {
         }
 
         // Choose a random neighbour to visit
-        let next_cell_direction = neighbors[random::<usize>() % neighbors.len()];
-        let next_cell_position = self.get_neighbor_cell_position(current_cell, next_cell_direction);
-        let next_cell = self.get_cell(next_cell_position);
+        let next_cell_direction = neighbors[self.rng.gen_range(0..neighbors.len())];
+        let next_cell_position = self
+            .cells
+            .neighbor(current_cell, next_cell_direction)
+            .unwrap();
+        let next_cell = &mut self.cells[next_cell_position];
 
         // Mark next cell as visited
         next_cell.visited = true;
 
         // Set paths between current cell and next cell
-        next_cell.paths |= direction::opposite(next_cell_direction);
-        self.get_cell(current_cell).paths |= next_cell_direction;
+        next_cell.paths |= direction::flag(next_cell_direction.opposite());
+        self.cells[current_cell].paths |= direction::flag(next_cell_direction);
 
         // Add next cell to the visit queue
         self.cells_to_visit.push(next_cell_position);
@@ -144,56 +334,149 @@ impl Game for Maze {
 
         return;
 
-        fn get_unvisited_neighbours(maze: &mut Maze, current_cell: Point) -> Vec<direction::Type> {
-            let mut neighbors = Vec::new();
-            add_unvisited_neighbour(maze, current_cell, &mut neighbors, direction::NORTH);
-            add_unvisited_neighbour(maze, current_cell, &mut neighbors, direction::EAST);
-            add_unvisited_neighbour(maze, current_cell, &mut neighbors, direction::SOUTH);
-            add_unvisited_neighbour(maze, current_cell, &mut neighbors, direction::WEST);
-            neighbors
-        }
-
-        fn add_unvisited_neighbour(
-            maze: &mut Maze,
-            current_cell: Point,
-            neighbors: &mut Vec<direction::Type>,
-            direction: direction::Type,
-        ) {
-            let neighbor = maze.get_neighbor_cell(current_cell, direction);
-            if let Some(cell) = neighbor {
-                if !cell.visited {
-                    neighbors.push(direction);
-                }
+        fn get_unvisited_neighbours(maze: &Maze, current_cell: Point) -> Vec<Direction> {
+            maze.cells
+                .neighbors(current_cell)
+                .filter(|&(_, neighbor)| !maze.cells[neighbor].visited)
+                .map(|(direction, _)| direction)
+                .collect()
+        }
+    }
+}
+
+impl Game for Maze {
+    fn handle_event(&mut self, event: Event) -> Option<Event> {
+        if self.show_settings && self.ui.handle_event(&event) {
+            return None;
+        }
+
+        if let Event::KeyDown {
+            key: Some(Key::F1),
+            repeat: false,
+        } = event
+        {
+            self.show_settings = !self.show_settings;
+            return None;
+        }
+
+        match event {
+            Event::KeyDown {
+                key: Some(Key::F5),
+                repeat: false,
+            } => {
+                self.save();
+                return None;
             }
+            // Loading needs the engine to release the tile cache, so it waits for `render`
+            Event::KeyDown {
+                key: Some(Key::F9),
+                repeat: false,
+            } => {
+                self.load_requested = true;
+                return None;
+            }
+            _ => {}
+        }
+
+        Some(event)
+    }
+
+    fn update(&mut self, dt: Float, engine: &Engine) {
+        if self.completed {
+            return;
+        }
+
+        // Carve at a rate of game time, so pausing and time scaling slow it down
+        self.game_time += dt;
+        self.carve_progress += dt * self.carve_rate;
+        while self.carve_progress >= 1.0 {
+            self.carve_progress -= 1.0;
+            self.carve_next_cell();
+        }
+
+        let cells = self.cells.width() * self.cells.height();
+        if self.num_visited_cells >= cells {
+            self.completed = true;
+            engine.publish(MazeCompleted {
+                cells,
+                game_time: self.game_time,
+            });
         }
     }
 
     fn render(&mut self, engine: &mut Engine) {
-        for x in 0..self.width as i32 {
-            for y in 0..self.height as i32 {
-                let cell = *self.get_cell_xy(x, y).unwrap();
-                self.draw_cell(x, y, cell, engine);
+        if self.load_requested {
+            self.load_requested = false;
+            self.load(engine);
+        }
+
+        // Whole maze is offset by 1 point for the border cell walls
+        const CELL_BORDER_OFFSET: Point = Point::new(1, 1);
+
+        // Each cell requires path_width + 1 for path on the side
+        let path_width = self.path_width;
+        self.cells.render(
+            engine,
+            CELL_BORDER_OFFSET,
+            path_width + 1,
+            |canvas, position, cell| draw_cell(canvas, position, cell, path_width),
+        );
+
+        if self.show_settings {
+            self.render_settings(engine);
+        }
+
+        // Counted in u64, so large mazes can't overflow and empty ones count as done
+        let cells = u64::from(self.cells.width()) * u64::from(self.cells.height());
+        let progress = (u64::from(self.num_visited_cells) * 100)
+            .checked_div(cells)
+            .unwrap_or(100) as u32;
+        if self.title_progress != Some(progress) {
+            self.title_progress = Some(progress);
+            let title = format!("{} - {}% carved", self.title, progress);
+            if let Err(error) = engine.set_title(&title) {
+                eprintln!("Could not set the title: {}", error);
             }
         }
     }
 }
 
 fn main() {
-    let width: u32 = 40;
-    let height: u32 = 20;
-    let path_width: u32 = 3;
+    let mut config = EngineConfig::load_with_args("maze.toml").unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let settings = config
+        .game::<MazeSettings>()
+        .map_err(|error| error.to_string())
+        .and_then(|settings| settings.validate().map(|()| settings))
+        .unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        });
+
+    // The same seed always carves the same maze
+    let seed = *config.seed.get_or_insert_with(random);
 
-    let mut maze = Maze::new(width, height, path_width);
-    maze.cells[0].visited = true;
-    maze.cells_to_visit.push(Point::new(0, 0));
-    maze.num_visited_cells = 1;
+    let title = config.title.clone().unwrap_or_else(|| String::from("Maze"));
+    let maze = Maze::new(settings.clone(), seed, title.clone());
 
     // Each cell requires path_width + 1 for path on the side.
     // Whole maze requires 1 extra point for the border cell walls.
-    let draw_width = width * (path_width + 1) + 1;
-    let draw_height = height * (path_width + 1) + 1;
+    let draw_width = settings.columns * (settings.path_width + 1) + 1;
+    let draw_height = settings.rows * (settings.path_width + 1) + 1;
 
-    engine::create(maze, String::from("Maze"))
+    let mut engine = engine::create(maze, title)
+        .with_config(config)
         .with_point_dimensions(draw_width, draw_height)
-        .start();
+        .build();
+
+    engine.subscribe(move |event: &MazeCompleted| {
+        println!(
+            "Carved {} cells in {:.1}s of game time (seed {})",
+            event.cells, event.game_time, seed
+        );
+    });
+
+    engine.start();
 }
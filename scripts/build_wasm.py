@@ -20,11 +20,18 @@ def build_sdl2(emsdk_path):
     ensure_success(ok, "Failed to build sdl2")
 
 def build_project(emsdk_path, project_name):
+    # Bundle the assets into the virtual file system, read by engine::assets
+    assets_path = project_name + "/assets"
+    if os.path.isdir(assets_path):
+        os.environ["EMCC_CFLAGS"] += " --preload-file " + assets_path + "@assets"
+
     ok = os.system(emsdk_path + "/emsdk activate latest && cargo build --bin " + project_name + 
                    " --target wasm32-unknown-emscripten --manifest-path " + project_name + "/Cargo.toml --release")
     ensure_success(ok, "Failed to build " + project_name + " for WASM")
     shutil.copy("./target/wasm32-unknown-emscripten/release/" + project_name.replace("-", "_") + ".wasm", "./pages/")
     shutil.copy("./target/wasm32-unknown-emscripten/release/" + project_name + ".js", "./pages/")
+    if os.path.isdir(assets_path):
+        shutil.copy("./target/wasm32-unknown-emscripten/release/" + project_name + ".data", "./pages/")
 
 def main():
     # Ensure that the user has provided the path to emsdk